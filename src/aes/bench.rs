@@ -0,0 +1,59 @@
+//! Rough throughput numbers for the AES backends, run with
+//! `cargo run --release -- bench-aes`.
+//!
+//! The "per call" rows set up the key for every block, which is what
+//! `encrypt_aes_128_ecb` did with a fresh OpenSSL `Crypter` per call, and the
+//! rest expand the key once and reuse it.
+
+use std::{hint::black_box, time::Instant};
+
+use super::{soft, Aes, FastAes};
+
+/// Time encrypting `blocks` blocks with each backend and print the results.
+pub fn run(blocks: usize) {
+    let key = *b"YELLOW SUBMARINE";
+    println!("Encrypting {} blocks", blocks);
+
+    time("soft, per call", blocks, |block| soft::Aes::new(&key).encrypt_block(block));
+    let aes = soft::Aes::new(&key);
+    time("soft, reused key", blocks, |block| aes.encrypt_block(block));
+
+    #[cfg(target_arch = "x86_64")]
+    if super::ni::is_supported() {
+        time("AES-NI, per call", blocks, |block| super::ni::AesNi::new(&key).unwrap().encrypt_block(block));
+        let aes = super::ni::AesNi::new(&key).unwrap();
+        time("AES-NI, reused key", blocks, |block| aes.encrypt_block(block));
+    } else {
+        println!("AES-NI not supported on this CPU");
+    }
+    let aes = FastAes::new(&key);
+    time("FastAes, reused key", blocks, |block| aes.encrypt_block(block));
+
+    let aes = Aes::new(&key);
+    time("Aes, reused key", blocks, |block| aes.encrypt_block(block));
+    let mut data = vec![0u8; blocks * 16];
+    let start = Instant::now();
+    aes.encrypt_blocks(black_box(&mut data));
+    report("Aes, whole buffer", blocks, start);
+
+    #[cfg(feature = "openssl")]
+    time("OpenSSL, per call", blocks, |block| {
+        let ct = super::encrypt_block(block, &key);
+        block.copy_from_slice(&ct);
+    });
+}
+
+fn time(name: &str, blocks: usize, mut f: impl FnMut(&mut [u8; 16])) {
+    let mut block = [0u8; 16];
+    let start = Instant::now();
+    for _ in 0..blocks {
+        f(black_box(&mut block));
+    }
+    report(name, blocks, start);
+}
+
+fn report(name: &str, blocks: usize, start: Instant) {
+    let elapsed = start.elapsed();
+    let mb_per_sec = (blocks * 16) as f64 / elapsed.as_secs_f64() / 1_000_000.0;
+    println!("{:<22} {:>10.2?} {:>10.1} MB/s", name, elapsed, mb_per_sec);
+}
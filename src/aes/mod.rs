@@ -0,0 +1,238 @@
+//! Raw AES block encryption used by the cipher modes in the challenges.
+//!
+//! The backend is picked with the `openssl` cargo feature. It is on by
+//! default, and building with `--no-default-features` swaps in [`FastAes`],
+//! which uses AES-NI when the CPU has it and the pure Rust implementation in
+//! [`soft`] otherwise, and drops the (slow to build) vendored OpenSSL.
+
+pub mod bench;
+#[cfg(target_arch = "x86_64")]
+pub mod ni;
+pub mod soft;
+
+#[cfg(feature = "openssl")]
+use std::cell::RefCell;
+
+/// An expanded AES key using AES-NI when the CPU supports it, and the
+/// software implementation otherwise. The check is made once, when the key
+/// is expanded.
+#[derive(Clone)]
+pub enum FastAes {
+    #[cfg(target_arch = "x86_64")]
+    Ni(ni::AesNi),
+    Soft(soft::Aes),
+}
+
+impl FastAes {
+    /// Panics if the key is not 16, 24 or 32 bytes long.
+    pub fn new(key: &[u8]) -> FastAes {
+        #[cfg(target_arch = "x86_64")]
+        if let Some(aes) = ni::AesNi::new(key) {
+            return FastAes::Ni(aes);
+        }
+        FastAes::Soft(soft::Aes::new(key))
+    }
+
+    pub fn encrypt_block(&self, block: &mut [u8; 16]) {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            FastAes::Ni(aes) => aes.encrypt_block(block),
+            FastAes::Soft(aes) => aes.encrypt_block(block),
+        }
+    }
+
+    pub fn decrypt_block(&self, block: &mut [u8; 16]) {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            FastAes::Ni(aes) => aes.decrypt_block(block),
+            FastAes::Soft(aes) => aes.decrypt_block(block),
+        }
+    }
+}
+
+/// An AES key expanded once on the backend picked by the `openssl` feature,
+/// so blocks and whole buffers can be encrypted without setting the key up
+/// again for every call. The key length picks between AES-128, AES-192 and
+/// AES-256.
+pub struct Aes {
+    #[cfg(feature = "openssl")]
+    encrypter: RefCell<openssl::symm::Crypter>,
+    #[cfg(feature = "openssl")]
+    decrypter: RefCell<openssl::symm::Crypter>,
+    #[cfg(not(feature = "openssl"))]
+    inner: FastAes,
+}
+
+impl Aes {
+    /// Panics if the key is not 16, 24 or 32 bytes long.
+    #[cfg(feature = "openssl")]
+    pub fn new(key: &[u8]) -> Aes {
+        use openssl::symm::Mode;
+
+        Aes { encrypter: openssl_crypter(key, Mode::Encrypt), decrypter: openssl_crypter(key, Mode::Decrypt) }
+    }
+
+    /// Panics if the key is not 16, 24 or 32 bytes long.
+    #[cfg(not(feature = "openssl"))]
+    pub fn new(key: &[u8]) -> Aes {
+        Aes { inner: FastAes::new(key) }
+    }
+
+    pub fn encrypt_block(&self, block: &mut [u8; 16]) {
+        self.encrypt_blocks(block);
+    }
+
+    pub fn decrypt_block(&self, block: &mut [u8; 16]) {
+        self.decrypt_blocks(block);
+    }
+
+    /// Encrypt each 16 byte block of `data` in place, as in ECB mode. Panics
+    /// if `data` is not block aligned.
+    #[cfg(feature = "openssl")]
+    pub fn encrypt_blocks(&self, data: &mut [u8]) {
+        openssl_blocks(&self.encrypter, data);
+    }
+
+    /// Decrypt each 16 byte block of `data` in place, as in ECB mode. Panics
+    /// if `data` is not block aligned.
+    #[cfg(feature = "openssl")]
+    pub fn decrypt_blocks(&self, data: &mut [u8]) {
+        openssl_blocks(&self.decrypter, data);
+    }
+
+    /// Encrypt each 16 byte block of `data` in place, as in ECB mode. Panics
+    /// if `data` is not block aligned.
+    #[cfg(not(feature = "openssl"))]
+    pub fn encrypt_blocks(&self, data: &mut [u8]) {
+        assert!(data.len().is_multiple_of(16), "data is not block aligned");
+        for block in data.chunks_exact_mut(16) {
+            self.inner.encrypt_block(block.try_into().unwrap());
+        }
+    }
+
+    /// Decrypt each 16 byte block of `data` in place, as in ECB mode. Panics
+    /// if `data` is not block aligned.
+    #[cfg(not(feature = "openssl"))]
+    pub fn decrypt_blocks(&self, data: &mut [u8]) {
+        assert!(data.len().is_multiple_of(16), "data is not block aligned");
+        for block in data.chunks_exact_mut(16) {
+            self.inner.decrypt_block(block.try_into().unwrap());
+        }
+    }
+}
+
+/// An unpadded ECB `Crypter` for one direction. Panics if the key is not
+/// 16, 24 or 32 bytes long.
+#[cfg(feature = "openssl")]
+fn openssl_crypter(key: &[u8], mode: openssl::symm::Mode) -> RefCell<openssl::symm::Crypter> {
+    use openssl::symm::{Cipher, Crypter};
+
+    let c = match key.len() {
+        16 => Cipher::aes_128_ecb(),
+        24 => Cipher::aes_192_ecb(),
+        32 => Cipher::aes_256_ecb(),
+        n => panic!("AES keys must be 16, 24 or 32 bytes, got {}", n),
+    };
+    let mut crypter = Crypter::new(c, mode, key, None).unwrap();
+    crypter.pad(false);
+    RefCell::new(crypter)
+}
+
+/// Run a whole buffer through a reused ECB `Crypter` with one call.
+#[cfg(feature = "openssl")]
+fn openssl_blocks(crypter: &RefCell<openssl::symm::Crypter>, data: &mut [u8]) {
+    assert!(data.len().is_multiple_of(16), "data is not block aligned");
+    let mut out = vec![0u8; data.len() + 16];
+    let n = crypter.borrow_mut().update(data, &mut out).unwrap();
+    debug_assert_eq!(data.len(), n);
+    data.copy_from_slice(&out[..data.len()]);
+}
+
+/// [`Aes`] with the key size fixed to 128 bits.
+pub struct Aes128(Aes);
+
+impl Aes128 {
+    pub fn new(key: &[u8; 16]) -> Aes128 {
+        Aes128(Aes::new(key))
+    }
+}
+
+impl std::ops::Deref for Aes128 {
+    type Target = Aes;
+
+    fn deref(&self) -> &Aes {
+        &self.0
+    }
+}
+
+/// Encrypt a single 16 byte block. The key length picks between AES-128,
+/// AES-192 and AES-256. This sets the key up on every call, so use [`Aes`]
+/// for more than one block.
+pub fn encrypt_block(block: &[u8], key: &[u8]) -> [u8; 16] {
+    let mut res = [0u8; 16];
+    res.copy_from_slice(&block[..16]);
+    // Only set up the one direction needed, not both as Aes::new does.
+    #[cfg(feature = "openssl")]
+    openssl_blocks(&openssl_crypter(key, openssl::symm::Mode::Encrypt), &mut res);
+    #[cfg(not(feature = "openssl"))]
+    Aes::new(key).encrypt_block(&mut res);
+    res
+}
+
+/// Decrypt a single 16 byte block. The key length picks between AES-128,
+/// AES-192 and AES-256. This sets the key up on every call, so use [`Aes`]
+/// for more than one block.
+pub fn decrypt_block(block: &[u8], key: &[u8]) -> [u8; 16] {
+    let mut res = [0u8; 16];
+    res.copy_from_slice(&block[..16]);
+    // Only set up the one direction needed, not both as Aes::new does.
+    #[cfg(feature = "openssl")]
+    openssl_blocks(&openssl_crypter(key, openssl::symm::Mode::Decrypt), &mut res);
+    #[cfg(not(feature = "openssl"))]
+    Aes::new(key).decrypt_block(&mut res);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{RngCore, rngs::OsRng};
+
+    use super::*;
+
+    #[test]
+    fn aes_matches_soft() {
+        for key_len in [16, 24, 32] {
+            let mut key = vec![0u8; key_len];
+            let mut data = vec![0u8; 16 * 20];
+            OsRng.fill_bytes(&mut key);
+            OsRng.fill_bytes(&mut data);
+
+            let soft = soft::Aes::new(&key);
+            let mut expected = data.clone();
+            for block in expected.chunks_exact_mut(16) {
+                soft.encrypt_block(block.try_into().unwrap());
+            }
+
+            let aes = Aes::new(&key);
+            let mut actual = data.clone();
+            aes.encrypt_blocks(&mut actual);
+            assert_eq!(expected, actual);
+            // The context is reused, so a second buffer must come out the same.
+            let mut again = data.clone();
+            aes.encrypt_blocks(&mut again);
+            assert_eq!(expected, again);
+            aes.decrypt_blocks(&mut actual);
+            assert_eq!(data, actual);
+        }
+    }
+
+    #[test]
+    fn aes_128_single_block() {
+        let aes = Aes128::new(b"YELLOW SUBMARINE");
+        let mut block = *b"sixteen byte msg";
+        aes.encrypt_block(&mut block);
+        assert_eq!(encrypt_block(b"sixteen byte msg", b"YELLOW SUBMARINE"), block);
+        aes.decrypt_block(&mut block);
+        assert_eq!(*b"sixteen byte msg", block);
+    }
+}
@@ -0,0 +1,142 @@
+//! AES using the x86_64 AES-NI instructions. The key schedule is shared
+//! with [`super::soft`], and only the rounds use the hardware instructions.
+//!
+//! An [`AesNi`] can only be built once the CPU has been checked for AES-NI
+//! support, which is what makes calling the `target_feature` functions
+//! sound.
+
+use std::arch::x86_64::{
+    __m128i, _mm_aesdec_si128, _mm_aesdeclast_si128, _mm_aesenc_si128, _mm_aesenclast_si128, _mm_aesimc_si128,
+    _mm_loadu_si128, _mm_storeu_si128, _mm_xor_si128,
+};
+
+use super::soft::expand_key;
+
+/// Whether the CPU supports the AES-NI instructions.
+pub fn is_supported() -> bool {
+    is_x86_feature_detected!("aes")
+}
+
+/// An AES key schedule for AES-NI. The key length picks the variant, as in
+/// [`super::soft::Aes`].
+#[derive(Clone)]
+pub struct AesNi {
+    enc_keys: Vec<__m128i>,
+    /// The round keys in reverse, with InvMixColumns applied to the middle
+    /// ones for the equivalent inverse cipher AESDEC implements.
+    dec_keys: Vec<__m128i>,
+}
+
+impl AesNi {
+    /// Expand the key, or None if the CPU doesn't support AES-NI. Panics if
+    /// the key is not 16, 24 or 32 bytes long.
+    pub fn new(key: &[u8]) -> Option<AesNi> {
+        if !is_supported() {
+            return None;
+        }
+        let round_keys = expand_key(key);
+        // SAFETY: AES-NI support was checked above.
+        Some(unsafe { AesNi::from_round_keys(&round_keys) })
+    }
+
+    #[target_feature(enable = "aes")]
+    unsafe fn from_round_keys(round_keys: &[[u8; 16]]) -> AesNi {
+        let enc_keys: Vec<__m128i> =
+            round_keys.iter().map(|rk| _mm_loadu_si128(rk.as_ptr() as *const __m128i)).collect();
+        let nr = enc_keys.len() - 1;
+        let mut dec_keys = Vec::with_capacity(nr + 1);
+        dec_keys.push(enc_keys[nr]);
+        for rk in enc_keys[1..nr].iter().rev() {
+            dec_keys.push(_mm_aesimc_si128(*rk));
+        }
+        dec_keys.push(enc_keys[0]);
+        AesNi { enc_keys, dec_keys }
+    }
+
+    pub fn encrypt_block(&self, block: &mut [u8; 16]) {
+        // SAFETY: an AesNi is only built when AES-NI is supported.
+        unsafe { self.encrypt_ni(block) }
+    }
+
+    pub fn decrypt_block(&self, block: &mut [u8; 16]) {
+        // SAFETY: an AesNi is only built when AES-NI is supported.
+        unsafe { self.decrypt_ni(block) }
+    }
+
+    #[target_feature(enable = "aes")]
+    unsafe fn encrypt_ni(&self, block: &mut [u8; 16]) {
+        let keys = &self.enc_keys;
+        let nr = keys.len() - 1;
+        let mut state = _mm_xor_si128(_mm_loadu_si128(block.as_ptr() as *const __m128i), keys[0]);
+        for rk in &keys[1..nr] {
+            state = _mm_aesenc_si128(state, *rk);
+        }
+        state = _mm_aesenclast_si128(state, keys[nr]);
+        _mm_storeu_si128(block.as_mut_ptr() as *mut __m128i, state);
+    }
+
+    #[target_feature(enable = "aes")]
+    unsafe fn decrypt_ni(&self, block: &mut [u8; 16]) {
+        let keys = &self.dec_keys;
+        let nr = keys.len() - 1;
+        let mut state = _mm_xor_si128(_mm_loadu_si128(block.as_ptr() as *const __m128i), keys[0]);
+        for rk in &keys[1..nr] {
+            state = _mm_aesdec_si128(state, *rk);
+        }
+        state = _mm_aesdeclast_si128(state, keys[nr]);
+        _mm_storeu_si128(block.as_mut_ptr() as *mut __m128i, state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{RngCore, rngs::OsRng};
+
+    use crate::{aes::soft::Aes, byte_util::{hex_decode, hex_encode}};
+
+    use super::*;
+
+    // FIPS-197 Appendix C
+    #[test]
+    fn aes_ni_example_vectors() {
+        let Some(_) = AesNi::new(&[0u8; 16]) else {
+            eprintln!("AES-NI not supported, skipping");
+            return;
+        };
+        for (key, expected) in [
+            ("000102030405060708090a0b0c0d0e0f", "69c4e0d86a7b0430d8cdb78070b4c55a"),
+            ("000102030405060708090a0b0c0d0e0f1011121314151617", "dda97ca4864cdfe06eaf70a0ec0d7191"),
+            ("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f", "8ea2b7ca516745bfeafc49904b496089"),
+        ] {
+            let aes = AesNi::new(&hex_decode(key.as_bytes())).unwrap();
+            let mut block = [0u8; 16];
+            block.copy_from_slice(&hex_decode("00112233445566778899aabbccddeeff".as_bytes()));
+            aes.encrypt_block(&mut block);
+            assert_eq!(expected.as_bytes(), hex_encode(&block));
+            aes.decrypt_block(&mut block);
+            assert_eq!("00112233445566778899aabbccddeeff".as_bytes(), hex_encode(&block));
+        }
+    }
+
+    #[test]
+    fn aes_ni_matches_soft() {
+        if !is_supported() {
+            eprintln!("AES-NI not supported, skipping");
+            return;
+        }
+        for key_len in [16, 24, 32] {
+            for _ in 0..50 {
+                let mut key = vec![0u8; key_len];
+                let mut block = [0u8; 16];
+                OsRng.fill_bytes(&mut key);
+                OsRng.fill_bytes(&mut block);
+                let (mut expected, mut actual) = (block, block);
+                Aes::new(&key).encrypt_block(&mut expected);
+                AesNi::new(&key).unwrap().encrypt_block(&mut actual);
+                assert_eq!(expected, actual);
+                AesNi::new(&key).unwrap().decrypt_block(&mut actual);
+                assert_eq!(block, actual);
+            }
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! A from scratch implementation of the AES block cipher, as specified in
+//! [FIPS-197](https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.197.pdf).
+//!
+//! The state is kept as 16 bytes in column major order, so byte `i` of a
+//! block is row `i % 4` and column `i / 4` of the state, which lines up with
+//! how the input bytes are read in the spec.
+
+const SBOX: [u8; 256] = [
+    0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+    0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+    0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+    0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+    0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+    0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+    0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+    0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+    0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+    0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+    0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+    0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+    0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+    0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+    0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+    0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+];
+
+const INV_SBOX: [u8; 256] = invert_sbox(&SBOX);
+
+/// Round constants for the key schedule, the powers of x in GF(2^8).
+const RCON: [u8; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+const fn invert_sbox(sbox: &[u8; 256]) -> [u8; 256] {
+    let mut res = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        res[sbox[i] as usize] = i as u8;
+        i += 1;
+    }
+    res
+}
+
+/// An AES key schedule. The key length picks the variant, 16, 24 and 32
+/// byte keys give AES-128, AES-192 and AES-256 respectively.
+#[derive(Clone)]
+pub struct Aes {
+    round_keys: Vec<[u8; 16]>,
+}
+
+impl Aes {
+    /// Expand the key into the round keys. Panics if the key is not 16, 24
+    /// or 32 bytes long.
+    pub fn new(key: &[u8]) -> Aes {
+        Aes { round_keys: expand_key(key) }
+    }
+
+    /// The number of rounds, 10, 12 or 14 depending on the key size.
+    pub fn rounds(&self) -> usize {
+        self.round_keys.len() - 1
+    }
+
+    pub fn encrypt_block(&self, block: &mut [u8; 16]) {
+        let nr = self.rounds();
+        add_round_key(block, &self.round_keys[0]);
+        for round in 1..nr {
+            sub_bytes(block);
+            shift_rows(block);
+            mix_columns(block);
+            add_round_key(block, &self.round_keys[round]);
+        }
+        sub_bytes(block);
+        shift_rows(block);
+        add_round_key(block, &self.round_keys[nr]);
+    }
+
+    pub fn decrypt_block(&self, block: &mut [u8; 16]) {
+        let nr = self.rounds();
+        add_round_key(block, &self.round_keys[nr]);
+        for round in (1..nr).rev() {
+            inv_shift_rows(block);
+            inv_sub_bytes(block);
+            add_round_key(block, &self.round_keys[round]);
+            inv_mix_columns(block);
+        }
+        inv_shift_rows(block);
+        inv_sub_bytes(block);
+        add_round_key(block, &self.round_keys[0]);
+    }
+}
+
+/// Expand a cipher key into Nr + 1 round keys. The schedule is built as
+/// 4 byte words, where every Nk-th word is rotated, substituted and mixed
+/// with a round constant, and AES-256 additionally substitutes the word
+/// halfway between.
+pub fn expand_key(key: &[u8]) -> Vec<[u8; 16]> {
+    let nk = match key.len() {
+        16 | 24 | 32 => key.len() / 4,
+        n => panic!("AES keys must be 16, 24 or 32 bytes, got {}", n),
+    };
+    let nr = nk + 6;
+    let mut words: Vec<[u8; 4]> = key.chunks(4).map(|w| [w[0], w[1], w[2], w[3]]).collect();
+    for i in nk..4 * (nr + 1) {
+        let mut tmp = words[i - 1];
+        if i % nk == 0 {
+            tmp.rotate_left(1);
+            tmp = tmp.map(|b| SBOX[b as usize]);
+            tmp[0] ^= RCON[i / nk - 1];
+        } else if nk > 6 && i % nk == 4 {
+            tmp = tmp.map(|b| SBOX[b as usize]);
+        }
+        let prev = words[i - nk];
+        words.push([prev[0] ^ tmp[0], prev[1] ^ tmp[1], prev[2] ^ tmp[2], prev[3] ^ tmp[3]]);
+    }
+
+    words
+        .chunks(4)
+        .map(|ws| {
+            let mut round_key = [0u8; 16];
+            for (j, w) in ws.iter().enumerate() {
+                round_key[4 * j..4 * j + 4].copy_from_slice(w);
+            }
+            round_key
+        })
+        .collect()
+}
+
+fn add_round_key(block: &mut [u8; 16], round_key: &[u8; 16]) {
+    for (b, k) in block.iter_mut().zip(round_key) {
+        *b ^= k;
+    }
+}
+
+fn sub_bytes(block: &mut [u8; 16]) {
+    for b in block.iter_mut() {
+        *b = SBOX[*b as usize];
+    }
+}
+
+fn inv_sub_bytes(block: &mut [u8; 16]) {
+    for b in block.iter_mut() {
+        *b = INV_SBOX[*b as usize];
+    }
+}
+
+/// Row r of the state is rotated left by r columns.
+fn shift_rows(block: &mut [u8; 16]) {
+    let s = *block;
+    for c in 0..4 {
+        for r in 0..4 {
+            block[4 * c + r] = s[4 * ((c + r) % 4) + r];
+        }
+    }
+}
+
+fn inv_shift_rows(block: &mut [u8; 16]) {
+    let s = *block;
+    for c in 0..4 {
+        for r in 0..4 {
+            block[4 * ((c + r) % 4) + r] = s[4 * c + r];
+        }
+    }
+}
+
+/// Multiply by x in GF(2^8), reducing by the AES polynomial
+/// x^8 + x^4 + x^3 + x + 1.
+fn xtime(b: u8) -> u8 {
+    (b << 1) ^ if b & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+/// Multiply two elements of GF(2^8).
+fn gmul(mut a: u8, mut b: u8) -> u8 {
+    let mut res = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            res ^= a;
+        }
+        a = xtime(a);
+        b >>= 1;
+    }
+    res
+}
+
+/// Each column is treated as a polynomial over GF(2^8) and multiplied by
+/// 3x^3 + x^2 + x + 2.
+fn mix_columns(block: &mut [u8; 16]) {
+    for col in block.chunks_mut(4) {
+        let [a0, a1, a2, a3] = [col[0], col[1], col[2], col[3]];
+        col[0] = xtime(a0) ^ xtime(a1) ^ a1 ^ a2 ^ a3;
+        col[1] = a0 ^ xtime(a1) ^ xtime(a2) ^ a2 ^ a3;
+        col[2] = a0 ^ a1 ^ xtime(a2) ^ xtime(a3) ^ a3;
+        col[3] = xtime(a0) ^ a0 ^ a1 ^ a2 ^ xtime(a3);
+    }
+}
+
+/// Multiplies each column by the inverse polynomial 11x^3 + 13x^2 + 9x + 14.
+fn inv_mix_columns(block: &mut [u8; 16]) {
+    for col in block.chunks_mut(4) {
+        let [a0, a1, a2, a3] = [col[0], col[1], col[2], col[3]];
+        col[0] = gmul(a0, 14) ^ gmul(a1, 11) ^ gmul(a2, 13) ^ gmul(a3, 9);
+        col[1] = gmul(a0, 9) ^ gmul(a1, 14) ^ gmul(a2, 11) ^ gmul(a3, 13);
+        col[2] = gmul(a0, 13) ^ gmul(a1, 9) ^ gmul(a2, 14) ^ gmul(a3, 11);
+        col[3] = gmul(a0, 11) ^ gmul(a1, 13) ^ gmul(a2, 9) ^ gmul(a3, 14);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::byte_util::{hex_decode, hex_encode};
+
+    use super::*;
+
+    fn check_vector(key: &str, plaintext: &str, ciphertext: &str) {
+        let aes = Aes::new(&hex_decode(key.as_bytes()));
+        let mut block = [0u8; 16];
+        block.copy_from_slice(&hex_decode(plaintext.as_bytes()));
+        aes.encrypt_block(&mut block);
+        assert_eq!(ciphertext.as_bytes(), hex_encode(&block));
+        aes.decrypt_block(&mut block);
+        assert_eq!(plaintext.as_bytes(), hex_encode(&block));
+    }
+
+    // FIPS-197 Appendix B
+    #[test]
+    fn aes_128_cipher_example() {
+        check_vector(
+            "2b7e151628aed2a6abf7158809cf4f3c",
+            "3243f6a8885a308d313198a2e0370734",
+            "3925841d02dc09fbdc118597196a0b32",
+        );
+    }
+
+    // FIPS-197 Appendix C.1
+    #[test]
+    fn aes_128_example_vector() {
+        check_vector(
+            "000102030405060708090a0b0c0d0e0f",
+            "00112233445566778899aabbccddeeff",
+            "69c4e0d86a7b0430d8cdb78070b4c55a",
+        );
+    }
+
+    // FIPS-197 Appendix C.2
+    #[test]
+    fn aes_192_example_vector() {
+        check_vector(
+            "000102030405060708090a0b0c0d0e0f1011121314151617",
+            "00112233445566778899aabbccddeeff",
+            "dda97ca4864cdfe06eaf70a0ec0d7191",
+        );
+    }
+
+    // FIPS-197 Appendix C.3
+    #[test]
+    fn aes_256_example_vector() {
+        check_vector(
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "00112233445566778899aabbccddeeff",
+            "8ea2b7ca516745bfeafc49904b496089",
+        );
+    }
+
+    // FIPS-197 Appendix A.1, the last round key of the expanded 128 bit key.
+    #[test]
+    fn expand_key_128() {
+        let round_keys = expand_key(&hex_decode("2b7e151628aed2a6abf7158809cf4f3c".as_bytes()));
+        assert_eq!(11, round_keys.len());
+        assert_eq!("d014f9a8c9ee2589e13f0cc8b6630ca6".as_bytes(), hex_encode(&round_keys[10]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_key_length() {
+        Aes::new("YELLOW SUB".as_bytes());
+    }
+
+    #[cfg(feature = "openssl")]
+    #[test]
+    fn matches_openssl() {
+        use openssl::symm::{Cipher, Crypter, Mode};
+        use rand::{RngCore, rngs::OsRng};
+
+        for (key_len, cipher) in [
+            (16, Cipher::aes_128_ecb()),
+            (24, Cipher::aes_192_ecb()),
+            (32, Cipher::aes_256_ecb()),
+        ] {
+            for _ in 0..50 {
+                let mut key = vec![0u8; key_len];
+                let mut block = [0u8; 16];
+                OsRng.fill_bytes(&mut key);
+                OsRng.fill_bytes(&mut block);
+
+                let mut crypter = Crypter::new(cipher, Mode::Encrypt, &key, None).unwrap();
+                crypter.pad(false);
+                let mut expected = vec![0; 32];
+                crypter.update(&block, &mut expected).unwrap();
+
+                let aes = Aes::new(&key);
+                let mut actual = block;
+                aes.encrypt_block(&mut actual);
+                assert_eq!(expected[..16], actual);
+                aes.decrypt_block(&mut actual);
+                assert_eq!(block, actual);
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Runs the attacks from the command line against freshly keyed oracles.
+//! Every key, IV, prefix and mode choice comes from the one RNG passed in,
+//! so a run can be replayed exactly by seeding it the same way.
+
+use std::str::from_utf8;
+
+use rand::{rngs::StdRng, Rng};
+
+use crate::{
+    oracle::{EncryptionOracle, Instrumented, ValidationOracle},
+    set_2::{
+        bitflip::{make_admin_cbc, CommentOracle},
+        byte_at_a_time::{byte_at_a_time, byte_at_a_time_prefix, challenge_12_secret, PrefixOracle, SuffixOracle},
+        oracle::{detect_mode, ModeOracle, DETECTION_INPUT},
+        profile::{make_admin, ProfileOracle},
+    },
+    set_3::{
+        mt_cipher::{encrypt_with_random_prefix, recover_mt_key},
+        mt_seed::{crack_timestamp_seed, wait_and_seed, Clock, SimulatedClock},
+        padding_oracle::{padding_oracle_attack, CbcPaddingOracle},
+    },
+    set_4::ctr_edit::{challenge_25_plaintext, recover_ctr_plaintext, CtrEditOracle},
+};
+
+/// The challenges that can be run.
+pub const CHALLENGES: [u32; 9] = [11, 12, 13, 14, 16, 17, 22, 24, 25];
+
+/// Run the attack for a challenge and print what it recovered.
+pub fn run(number: u32, rng: &mut StdRng) -> Result<(), String> {
+    match number {
+        11 => {
+            let mut oracle = ModeOracle::new(rng);
+            let trials = 100;
+            let mut right = 0;
+            for _ in 0..trials {
+                let ct = oracle.encrypt(&DETECTION_INPUT);
+                if oracle.last_mode() == Some(detect_mode(&ct)) {
+                    right += 1;
+                }
+            }
+            println!("Detected the mode {}/{} times", right, trials);
+        }
+        12 => {
+            let mut oracle = Instrumented::new(SuffixOracle::new(&challenge_12_secret(), rng));
+            let secret = byte_at_a_time(&mut oracle).ok_or("the oracle isn't ECB")?;
+            print_recovered(&secret, oracle.queries());
+        }
+        13 => {
+            let oracle = ProfileOracle::new(rng);
+            let ct = make_admin(|email| oracle.encrypt_profile(email));
+            let profile = oracle.decrypt_profile(&ct).ok_or("the forged profile didn't parse")?;
+            println!("{:?}", profile);
+        }
+        14 => {
+            let max_prefix = rng.gen_range(0..=64);
+            let mut oracle = Instrumented::new(PrefixOracle::new(&challenge_12_secret(), max_prefix, rng));
+            let secret = byte_at_a_time_prefix(&mut oracle).ok_or("the oracle isn't ECB")?;
+            print_recovered(&secret, oracle.queries());
+        }
+        16 => {
+            let mut oracle = Instrumented::new(CommentOracle::new(rng));
+            let ct = make_admin_cbc(&mut oracle).ok_or("the oracle's ciphertext was too short")?;
+            println!("Admin: {} after {} queries", oracle.validate(&ct), oracle.queries());
+        }
+        17 => {
+            let mut oracle = Instrumented::new(CbcPaddingOracle::new(rng));
+            let (iv, ct) = oracle.get_ref().encrypt_random_string(rng);
+            let plaintext = padding_oracle_attack(&mut oracle, &iv, &ct).ok_or("the padding oracle attack failed")?;
+            print_recovered(&plaintext, oracle.queries());
+        }
+        22 => {
+            // Simulated, so the waiting doesn't take the better part of half an hour.
+            let mut clock = SimulatedClock::new(rng.gen_range(1_000_000_000..2_000_000_000));
+            let output = wait_and_seed(&mut clock, rng);
+            let now = clock.now();
+            let seed = crack_timestamp_seed(output, now - 2000, now).ok_or("no seed in the last 2000 seconds")?;
+            println!("Seeded with {}, {} seconds before the output was seen", seed, now - seed);
+        }
+        24 => {
+            let key = rng.gen();
+            let known = [b'A'; 14];
+            let ct = encrypt_with_random_prefix(&known, key, rng);
+            let recovered = recover_mt_key(&ct, &known).ok_or("no key decrypts to the known plaintext")?;
+            println!("Recovered key {:#06x}", recovered);
+        }
+        25 => {
+            let (oracle, ct) = CtrEditOracle::new(&challenge_25_plaintext(), rng);
+            let mut oracle = Instrumented::new(oracle);
+            let plaintext = recover_ctr_plaintext(&mut oracle, &ct);
+            print_recovered(&plaintext, oracle.queries());
+        }
+        n => return Err(format!("no attack for challenge {}, try one of {:?}", n, CHALLENGES)),
+    }
+    Ok(())
+}
+
+fn print_recovered(plaintext: &[u8], queries: usize) {
+    match from_utf8(plaintext) {
+        Ok(s) => println!("{}", s),
+        Err(_) => println!("{:?}", plaintext),
+    }
+    println!("Recovered {} bytes in {} queries", plaintext.len(), queries);
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn every_challenge_runs() {
+        for n in CHALLENGES {
+            run(n, &mut StdRng::seed_from_u64(n as u64)).unwrap();
+        }
+        assert!(run(1, &mut StdRng::seed_from_u64(1)).is_err());
+    }
+}
@@ -1,23 +1,41 @@
 use std::{fs::read_to_string, str::from_utf8};
 
 use clap::{Args, Parser, Subcommand};
+use rand::{rngs::{OsRng, StdRng}, RngCore, SeedableRng};
 use set_1::xor::repeating_key_xor;
+use set_3::mt_seed::{crack_timestamp_seed, Clock, SystemClock};
 
 use crate::byte_util::hex_encode;
 
+pub mod aes;
+pub mod challenges;
 pub mod set_1;
 pub mod set_2;
+pub mod set_3;
+pub mod set_4;
 pub mod byte_util;
+pub mod modes;
+pub mod oracle;
 
 #[derive(Parser)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Seed for all the randomness in the oracles, so a run can be replayed.
+    /// A random seed is picked and printed if this is left out.
+    #[arg(long, global = true)]
+    seed: Option<u64>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    XorCipher(XorCipher)
+    XorCipher(XorCipher),
+    /// Compare the throughput of the AES backends.
+    BenchAes(BenchAes),
+    /// Run a challenge's attack against a freshly keyed oracle.
+    Challenge(Challenge),
+    /// Find the timestamp an MT19937 was seeded with from its first output.
+    CrackSeed(CrackSeed),
 }
 
 #[derive(Args)]
@@ -30,6 +48,28 @@ struct XorCipher {
     data: Vec<String>,
 }
 
+#[derive(Args)]
+struct BenchAes {
+    #[arg(long, short, default_value_t = 1_000_000)]
+    blocks: usize,
+}
+
+#[derive(Args)]
+struct Challenge {
+    number: u32,
+}
+
+#[derive(Args)]
+struct CrackSeed {
+    output: u32,
+    /// The latest the seed could be, as a UNIX timestamp. Defaults to now.
+    #[arg(long)]
+    until: Option<u32>,
+    /// How many seconds before that to search.
+    #[arg(long, short, default_value_t = 86_400)]
+    window: u32,
+}
+
 fn main() {
     let cli = Cli::parse();
 
@@ -46,5 +86,24 @@ fn main() {
                 }
             }
         }
+        Commands::BenchAes(b) => aes::bench::run(b.blocks),
+        Commands::Challenge(c) => {
+            let seed = cli.seed.unwrap_or_else(|| OsRng.next_u64());
+            eprintln!("Seed: {}", seed);
+            if let Err(e) = challenges::run(c.number, &mut StdRng::seed_from_u64(seed)) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::CrackSeed(c) => {
+            let until = c.until.unwrap_or_else(|| SystemClock.now());
+            match crack_timestamp_seed(c.output, until.saturating_sub(c.window), until) {
+                Some(seed) => println!("{}", seed),
+                None => {
+                    eprintln!("no seed in the {} seconds up to {}", c.window, until);
+                    std::process::exit(1);
+                }
+            }
+        }
     }
 }
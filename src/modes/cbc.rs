@@ -0,0 +1,291 @@
+use super::{padding::PaddingError, BlockCipher, BlockMode, Padding};
+
+/// The ciphertext stealing variants from the addendum to NIST SP 800-38A.
+/// They only differ in the order of the last two ciphertext blocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cts {
+    /// The partial block comes before the last full block.
+    Cs1,
+    /// Swap the last two blocks, but only when the last block is partial, so
+    /// aligned input gives plain CBC.
+    Cs2,
+    /// Always swap the last two blocks, as used by Kerberos.
+    Cs3,
+}
+
+/// Cipher block chaining mode. Each plaintext block is XORed with the
+/// previous ciphertext block (or the IV for the first block) before being
+/// encrypted.
+pub struct Cbc<C: BlockCipher> {
+    cipher: C,
+    iv: Vec<u8>,
+    padding: Padding,
+}
+
+impl<C: BlockCipher> Cbc<C> {
+    /// Panics if the IV is not exactly one block long.
+    pub fn new(cipher: C, iv: &[u8], padding: Padding) -> Cbc<C> {
+        assert_eq!(cipher.block_size(), iv.len(), "IV must be one block long");
+        Cbc { cipher, iv: iv.to_vec(), padding }
+    }
+
+    fn encrypt_blocks(&self, data: &mut [u8]) {
+        let bs = self.cipher.block_size();
+        let mut prev_block = self.iv.clone();
+        for block in data.chunks_mut(bs) {
+            for (b, p) in block.iter_mut().zip(&prev_block) {
+                *b ^= p;
+            }
+            self.cipher.encrypt_block(block);
+            prev_block.copy_from_slice(block);
+        }
+    }
+
+    /// Unlike encryption, every block can be decrypted at once and the
+    /// chaining applied afterwards.
+    fn decrypt_blocks(&self, data: &mut [u8]) {
+        let bs = self.cipher.block_size();
+        let input = data.to_vec();
+        self.cipher.decrypt_blocks(data);
+        let mut prev_block = self.iv.as_slice();
+        for (block, ct) in data.chunks_mut(bs).zip(input.chunks(bs)) {
+            for (b, p) in block.iter_mut().zip(prev_block) {
+                *b ^= p;
+            }
+            prev_block = ct;
+        }
+    }
+}
+
+impl<C: BlockCipher> BlockMode for Cbc<C> {
+    fn encrypt(&self, input: &[u8]) -> Vec<u8> {
+        let mut res = self.padding.pad(input, self.cipher.block_size());
+        self.encrypt_blocks(&mut res);
+        res
+    }
+
+    fn decrypt(&self, input: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        let bs = self.cipher.block_size();
+        if !input.len().is_multiple_of(bs) {
+            return Err(PaddingError::InvalidLength);
+        }
+        let mut res = input.to_vec();
+        self.decrypt_blocks(&mut res);
+        self.padding.unpad(res, bs)
+    }
+}
+
+/// CBC with ciphertext stealing instead of padding, so the ciphertext is as
+/// long as the message. The message must be at least one block long, so
+/// unlike [`BlockMode`] both directions can fail.
+pub struct CbcCts<C: BlockCipher> {
+    cbc: Cbc<C>,
+    variant: Cts,
+}
+
+impl<C: BlockCipher> CbcCts<C> {
+    /// Panics if the IV is not exactly one block long.
+    pub fn new(cipher: C, iv: &[u8], variant: Cts) -> CbcCts<C> {
+        CbcCts { cbc: Cbc::new(cipher, iv, Padding::None), variant }
+    }
+
+    /// Encrypt the zero padded input as normal, then drop the tail of the
+    /// second to last ciphertext block. The last block already depends on
+    /// it, so it can be recovered on decryption. Fails if the input is
+    /// shorter than one block.
+    pub fn encrypt(&self, input: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        let (variant, cbc) = (self.variant, &self.cbc);
+        let bs = cbc.cipher.block_size();
+        if input.len() < bs {
+            return Err(PaddingError::InvalidLength);
+        }
+        let n = input.len().div_ceil(bs);
+        let d = input.len() - (n - 1) * bs;
+        let mut res = input.to_vec();
+        res.resize(n * bs, 0);
+        cbc.encrypt_blocks(&mut res);
+        if n == 1 {
+            return Ok(res);
+        }
+
+        let last = res[(n - 1) * bs..].to_vec();
+        res.truncate((n - 2) * bs + d);
+        if cts_swap(variant, d, bs) {
+            res.splice((n - 2) * bs..(n - 2) * bs, last);
+        } else {
+            res.extend_from_slice(&last);
+        }
+        Ok(res)
+    }
+
+    /// Fails if the ciphertext is shorter than one block.
+    pub fn decrypt(&self, input: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        let (variant, cbc) = (self.variant, &self.cbc);
+        let bs = cbc.cipher.block_size();
+        if input.len() < bs {
+            return Err(PaddingError::InvalidLength);
+        }
+        let n = input.len().div_ceil(bs);
+        let d = input.len() - (n - 1) * bs;
+        if n == 1 {
+            let mut res = input.to_vec();
+            cbc.decrypt_blocks(&mut res);
+            return Ok(res);
+        }
+
+        let tail = (n - 2) * bs;
+        let (stolen, last) = if cts_swap(variant, d, bs) {
+            (&input[tail + bs..], &input[tail..tail + bs])
+        } else {
+            (&input[tail..tail + d], &input[tail + d..])
+        };
+        // Decrypting the last block gives the second to last ciphertext block
+        // XORed with the zero padded final plaintext, so its tail is the
+        // stolen part of that ciphertext block.
+        let mut z = last.to_vec();
+        cbc.cipher.decrypt_block(&mut z);
+
+        let mut res = input[..tail].to_vec();
+        res.extend_from_slice(stolen);
+        res.extend_from_slice(&z[d..]);
+        res.extend_from_slice(last);
+        cbc.decrypt_blocks(&mut res);
+        res.truncate(input.len());
+        Ok(res)
+    }
+}
+
+/// Whether the last two blocks are swapped, given the length `d` of the
+/// final partial block.
+fn cts_swap(variant: Cts, d: usize, bs: usize) -> bool {
+    match variant {
+        Cts::Cs1 => false,
+        Cts::Cs2 => d != bs,
+        Cts::Cs3 => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use crate::{aes::soft::Aes, byte_util::{base64_decode, hex_decode, hex_encode}};
+    use crate::modes::ToyCipher;
+
+    use super::*;
+
+    #[test]
+    fn cbc_happy() {
+        let input = "This is a 48-byte message (exactly 3 AES blocks)".as_bytes();
+        let key = hex_decode("6c3ea0477630ce21a2ce334aa746c2cd".as_bytes());
+        let iv = hex_decode("c782dc4c098c66cbd9cd27d825682c81".as_bytes());
+        let expected = "d0a02b3836451753d493665d33f0e8862dea54cdb293abc7506939276772f8d5021c19216bad525c8579695d83ba2684".as_bytes();
+        let cbc = Cbc::new(Aes::new(&key), &iv, Padding::None);
+        let actual = cbc.encrypt(input);
+        assert_eq!(expected, hex_encode(&actual));
+        assert_eq!(input, cbc.decrypt(&actual).unwrap());
+    }
+
+    #[test]
+    fn cbc_challenge_10() {
+        let key = "YELLOW SUBMARINE".as_bytes();
+        let input = read_to_string("./data/set_2/ch10.txt").unwrap();
+        let input = base64_decode(input.as_bytes());
+        let cbc = Cbc::new(Aes::new(key), &[0u8; 16], Padding::Pkcs7);
+        let actual = cbc.decrypt(&input).unwrap();
+        assert!(actual.starts_with("I'm back and I'm ringin' the bell".as_bytes()));
+        assert_eq!(input, cbc.encrypt(&actual));
+    }
+
+    #[test]
+    fn cbc_toy_cipher_hides_repeats() {
+        let cbc = Cbc::new(ToyCipher { key: *b"toy key!" }, b"toy iv!!", Padding::Pkcs7);
+        let input = "ABCDEFGHABCDEFGH".as_bytes();
+        let ct = cbc.encrypt(input);
+        assert_eq!(24, ct.len());
+        assert_ne!(ct[..8], ct[8..16]);
+        assert_eq!(input, cbc.decrypt(&ct).unwrap());
+    }
+
+    #[test]
+    fn cbc_rejects_bad_padding() {
+        let key = "YELLOW SUBMARINE".as_bytes();
+        let ct = Cbc::new(Aes::new(key), &[0u8; 16], Padding::Pkcs7).encrypt("ICE ICE BABY".as_bytes());
+        // Flipping the last IV byte turns the final \x04 pad byte into \x05.
+        let mut iv = [0u8; 16];
+        iv[15] = 0x01;
+        let cbc = Cbc::new(Aes::new(key), &iv, Padding::Pkcs7);
+        assert_eq!(Err(PaddingError::InvalidPadByte), cbc.decrypt(&ct));
+    }
+
+    const KERBEROS_KEY: &str = "636869636b656e207465726979616b69";
+    const KERBEROS_PLAINTEXT: &str = "I would like the General Gau's Chicken, please, and wonton soup.";
+
+    // RFC 3962 appendix B, AES-128 CBC-CS3 with a zero IV.
+    #[test]
+    fn cts_cs3_rfc_3962() {
+        let key = hex_decode(KERBEROS_KEY.as_bytes());
+        let cbc = CbcCts::new(Aes::new(&key), &[0u8; 16], Cts::Cs3);
+        for (len, expected) in [
+            (17, "c6353568f2bf8cb4d8a580362da7ff7f97"),
+            (31, "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5"),
+            (32, "39312523a78662d5be7fcbcc98ebf5a897687268d6ecccc0c07b25e25ecfe584"),
+            (47, "97687268d6ecccc0c07b25e25ecfe584b3fffd940c16a18c1b5549d2f838029e39312523a78662d5be7fcbcc98ebf5"),
+            (48, "97687268d6ecccc0c07b25e25ecfe5849dad8bbb96c4cdc03bc103e1a194bbd839312523a78662d5be7fcbcc98ebf5a8"),
+        ] {
+            let input = &KERBEROS_PLAINTEXT.as_bytes()[..len];
+            let actual = cbc.encrypt(input).unwrap();
+            assert_eq!(expected.as_bytes(), hex_encode(&actual), "{}", len);
+            assert_eq!(input, cbc.decrypt(&actual).unwrap());
+        }
+    }
+
+    #[test]
+    fn cts_variants_reorder_last_blocks() {
+        let key = hex_decode(KERBEROS_KEY.as_bytes());
+        let plain = Cbc::new(Aes::new(&key), &[0u8; 16], Padding::None);
+        let cs1 = CbcCts::new(Aes::new(&key), &[0u8; 16], Cts::Cs1);
+        let cs2 = CbcCts::new(Aes::new(&key), &[0u8; 16], Cts::Cs2);
+        let cs3 = CbcCts::new(Aes::new(&key), &[0u8; 16], Cts::Cs3);
+
+        // Aligned input is plain CBC for CS1 and CS2, CS3 still swaps.
+        let aligned = &KERBEROS_PLAINTEXT.as_bytes()[..48];
+        let expected = plain.encrypt(aligned);
+        assert_eq!(expected, cs1.encrypt(aligned).unwrap());
+        assert_eq!(expected, cs2.encrypt(aligned).unwrap());
+        assert_eq!(expected[32..], cs3.encrypt(aligned).unwrap()[16..32]);
+
+        // Unaligned input, CS2 and CS3 agree and CS1 has the partial block first.
+        let input = &KERBEROS_PLAINTEXT.as_bytes()[..45];
+        let ct1 = cs1.encrypt(input).unwrap();
+        let ct2 = cs2.encrypt(input).unwrap();
+        assert_eq!(45, ct1.len());
+        assert_eq!(ct2, cs3.encrypt(input).unwrap());
+        assert_eq!(ct1[..16], ct2[..16]);
+        assert_eq!(ct1[16..29], ct2[32..]);
+        assert_eq!(ct1[29..], ct2[16..32]);
+        for cbc in [cs1, cs2, cs3] {
+            assert_eq!(input, cbc.decrypt(&cbc.encrypt(input).unwrap()).unwrap());
+        }
+    }
+
+    #[test]
+    fn cts_rejects_short_input() {
+        let cbc = CbcCts::new(Aes::new("YELLOW SUBMARINE".as_bytes()), &[0u8; 16], Cts::Cs1);
+        assert_eq!(Err(PaddingError::InvalidLength), cbc.encrypt(&[0u8; 15]));
+        assert_eq!(Err(PaddingError::InvalidLength), cbc.decrypt(&[0u8; 15]));
+        assert_eq!(Err(PaddingError::InvalidLength), cbc.encrypt(&[]));
+    }
+
+    #[test]
+    fn cbc_padding_schemes_round_trip() {
+        let key = "YELLOW SUBMARINE".as_bytes();
+        for padding in [Padding::Pkcs7, Padding::AnsiX923, Padding::Iso10126, Padding::Iso7816, Padding::Zero] {
+            let cbc = Cbc::new(Aes::new(key), &[0u8; 16], padding);
+            for n in 1..40 {
+                let input = vec![b'A'; n];
+                assert_eq!(input, cbc.decrypt(&cbc.encrypt(&input)).unwrap(), "{:?} {}", padding, n);
+            }
+        }
+    }
+}
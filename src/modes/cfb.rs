@@ -0,0 +1,139 @@
+use super::{BlockCipher, StreamMode};
+
+/// Cipher feedback mode. A shift register starting at the IV is encrypted,
+/// the top `segment_bits` of the output are XORed with the next segment of
+/// plaintext, and the resulting ciphertext segment is shifted into the
+/// register. No padding is needed, a short final segment is just truncated.
+pub struct Cfb<C: BlockCipher> {
+    cipher: C,
+    iv: Vec<u8>,
+    segment_bits: usize,
+}
+
+impl<C: BlockCipher> Cfb<C> {
+    /// Panics if the IV is not one block long, or the segment size isn't 1
+    /// bit or a whole number of bytes no larger than the block.
+    pub fn new(cipher: C, iv: &[u8], segment_bits: usize) -> Cfb<C> {
+        let bs = cipher.block_size();
+        assert_eq!(bs, iv.len(), "IV must be one block long");
+        assert!(
+            segment_bits == 1 || (segment_bits.is_multiple_of(8) && segment_bits > 0 && segment_bits <= bs * 8),
+            "CFB segments must be 1 bit or a whole number of bytes up to the block size"
+        );
+        Cfb { cipher, iv: iv.to_vec(), segment_bits }
+    }
+
+    fn process(&self, input: &[u8], decrypt: bool) -> Vec<u8> {
+        if self.segment_bits == 1 {
+            return self.process_bits(input, decrypt);
+        }
+        let seg = self.segment_bits / 8;
+        let mut register = self.iv.clone();
+        let mut res = Vec::with_capacity(input.len());
+        for segment in input.chunks(seg) {
+            let mut keystream = register.clone();
+            self.cipher.encrypt_block(&mut keystream);
+            let out: Vec<u8> = segment.iter().zip(&keystream).map(|(a, b)| a ^ b).collect();
+            // The ciphertext segment is what gets fed back in both directions.
+            let feedback = if decrypt { segment } else { &out };
+            register.rotate_left(seg);
+            let n = register.len();
+            register[n - seg..n - seg + feedback.len()].copy_from_slice(feedback);
+            res.extend_from_slice(&out);
+        }
+        res
+    }
+
+    /// CFB-1 works a bit at a time, most significant bit of each byte first.
+    fn process_bits(&self, input: &[u8], decrypt: bool) -> Vec<u8> {
+        let mut register = self.iv.clone();
+        let mut res = Vec::with_capacity(input.len());
+        for byte in input {
+            let mut out = 0u8;
+            for bit in (0..8).rev() {
+                let mut keystream = register.clone();
+                self.cipher.encrypt_block(&mut keystream);
+                let in_bit = (byte >> bit) & 1;
+                let out_bit = in_bit ^ (keystream[0] >> 7);
+                out |= out_bit << bit;
+                let feedback = if decrypt { in_bit } else { out_bit };
+                shift_left_one_bit(&mut register, feedback);
+            }
+            res.push(out);
+        }
+        res
+    }
+}
+
+/// Shift a big endian bit string left by one bit, shifting `bit` in at the
+/// bottom.
+fn shift_left_one_bit(register: &mut [u8], bit: u8) {
+    let mut carry = bit;
+    for b in register.iter_mut().rev() {
+        let next = *b >> 7;
+        *b = (*b << 1) | carry;
+        carry = next;
+    }
+}
+
+impl<C: BlockCipher> StreamMode for Cfb<C> {
+    fn encrypt(&self, input: &[u8]) -> Vec<u8> {
+        self.process(input, false)
+    }
+
+    fn decrypt(&self, input: &[u8]) -> Vec<u8> {
+        self.process(input, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes::soft::Aes, byte_util::{hex_decode, hex_encode}};
+    use crate::modes::ToyCipher;
+
+    use super::*;
+
+    const NIST_KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const NIST_IV: &str = "000102030405060708090a0b0c0d0e0f";
+    const NIST_PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e5130c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+    fn check_vector(segment_bits: usize, input: &str, expected: &str) {
+        let iv = hex_decode(NIST_IV.as_bytes());
+        let cfb = Cfb::new(Aes::new(&hex_decode(NIST_KEY.as_bytes())), &iv, segment_bits);
+        let input = hex_decode(input.as_bytes());
+        let actual = cfb.encrypt(&input);
+        assert_eq!(expected.as_bytes(), hex_encode(&actual));
+        assert_eq!(input, cfb.decrypt(&actual));
+    }
+
+    // NIST SP 800-38A F.3.1, the 16 bits of the example.
+    #[test]
+    fn cfb1_nist_vector() {
+        check_vector(1, "6bc1", "68b3");
+    }
+
+    // NIST SP 800-38A F.3.7
+    #[test]
+    fn cfb8_nist_vector() {
+        check_vector(8, "6bc1bee22e409f96e93d7e117393172aae2d", "3b79424c9c0dd436bace9e0ed4586a4f32b9");
+    }
+
+    // NIST SP 800-38A F.3.13
+    #[test]
+    fn cfb128_nist_vector() {
+        check_vector(
+            128,
+            NIST_PLAINTEXT,
+            "3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b26751f67a3cbb140b1808cf187a4f4dfc04b05357c5d1c0eeac4c66f9ff7f2e6",
+        );
+    }
+
+    #[test]
+    fn cfb_partial_segment() {
+        let cfb = Cfb::new(ToyCipher { key: *b"toy key!" }, b"toy iv!!", 32);
+        let input = "Eleven byte".as_bytes();
+        let actual = cfb.encrypt(input);
+        assert_eq!(11, actual.len());
+        assert_eq!(input, cfb.decrypt(&actual));
+    }
+}
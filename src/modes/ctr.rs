@@ -0,0 +1,254 @@
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use super::{BlockCipher, StreamMode};
+
+/// How the counter block fed to the cipher is built from the block index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CtrLayout {
+    /// A 64 bit little endian nonce followed by a 64 bit little endian block
+    /// counter starting at zero, as used in the cryptopals challenges.
+    LittleEndian64 { nonce: u64 },
+    /// The whole block is one 128 bit big endian counter starting at the
+    /// initial counter block, as in NIST SP 800-38A. It wraps on overflow.
+    BigEndian128 { initial: u128 },
+}
+
+impl CtrLayout {
+    fn counter_block(&self, index: u64) -> [u8; 16] {
+        let mut block = [0u8; 16];
+        match self {
+            CtrLayout::LittleEndian64 { nonce } => {
+                block[..8].copy_from_slice(&nonce.to_le_bytes());
+                block[8..].copy_from_slice(&index.to_le_bytes());
+            }
+            CtrLayout::BigEndian128 { initial } => {
+                block.copy_from_slice(&initial.wrapping_add(index as u128).to_be_bytes());
+            }
+        }
+        block
+    }
+}
+
+/// Counter mode. Successive counter blocks are encrypted to make a keystream
+/// which is XORed with the data, so encryption and decryption are the same
+/// operation, no padding is needed and any byte offset can be reached
+/// without touching the data before it.
+pub struct Ctr<C: BlockCipher> {
+    cipher: C,
+    layout: CtrLayout,
+}
+
+impl<C: BlockCipher> Ctr<C> {
+    /// Panics if the cipher doesn't have a 16 byte block.
+    pub fn new(cipher: C, layout: CtrLayout) -> Ctr<C> {
+        assert_eq!(16, cipher.block_size(), "CTR counter layouts need a 16 byte block");
+        Ctr { cipher, layout }
+    }
+
+    /// The keystream block for the given block index.
+    pub fn keystream_block(&self, index: u64) -> [u8; 16] {
+        let mut block = self.layout.counter_block(index);
+        self.cipher.encrypt_block(&mut block);
+        block
+    }
+
+    /// XOR the keystream into `data` in place, treating `data` as starting
+    /// at byte `offset` of the stream.
+    pub fn apply_keystream(&self, data: &mut [u8], offset: u64) {
+        let mut pos = offset;
+        let mut data = data;
+        while !data.is_empty() {
+            let keystream = self.keystream_block(pos / 16);
+            let skip = (pos % 16) as usize;
+            let n = (16 - skip).min(data.len());
+            for (d, k) in data[..n].iter_mut().zip(&keystream[skip..]) {
+                *d ^= k;
+            }
+            data = &mut data[n..];
+            pos += n as u64;
+        }
+    }
+
+    /// Encrypt or decrypt `input` as if it sat at byte `offset` of the
+    /// stream.
+    pub fn process_at(&self, input: &[u8], offset: u64) -> Vec<u8> {
+        let mut res = input.to_vec();
+        self.apply_keystream(&mut res, offset);
+        res
+    }
+
+    /// Replace the plaintext at byte `offset` of a ciphertext with
+    /// `newtext`, re-encrypting only those bytes. The ciphertext grows if the
+    /// new text runs past its end. Panics if `offset` is past the end.
+    pub fn edit(&self, ciphertext: &[u8], offset: usize, newtext: &[u8]) -> Vec<u8> {
+        assert!(offset <= ciphertext.len(), "edit offset is past the end of the ciphertext");
+        let end = offset + newtext.len();
+        let mut res = ciphertext.to_vec();
+        res.resize(res.len().max(end), 0);
+        res[offset..end].copy_from_slice(newtext);
+        self.apply_keystream(&mut res[offset..end], offset as u64);
+        res
+    }
+}
+
+impl<C: BlockCipher> StreamMode for Ctr<C> {
+    fn encrypt(&self, input: &[u8]) -> Vec<u8> {
+        self.process_at(input, 0)
+    }
+
+    fn decrypt(&self, input: &[u8]) -> Vec<u8> {
+        self.process_at(input, 0)
+    }
+}
+
+/// Wraps a reader or writer of CTR ciphertext. Reads decrypt, writes
+/// encrypt, and seeking moves the keystream along with the inner stream,
+/// so a large encrypted file can be read or edited in place.
+pub struct CtrStream<C: BlockCipher, S> {
+    ctr: Ctr<C>,
+    inner: S,
+    pos: u64,
+}
+
+impl<C: BlockCipher, S> CtrStream<C, S> {
+    /// The inner stream is assumed to be at the start of the ciphertext.
+    pub fn new(ctr: Ctr<C>, inner: S) -> CtrStream<C, S> {
+        CtrStream { ctr, inner, pos: 0 }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<C: BlockCipher, S: Read> Read for CtrStream<C, S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.ctr.apply_keystream(&mut buf[..n], self.pos);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<C: BlockCipher, S: Write> Write for CtrStream<C, S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let ct = self.ctr.process_at(buf, self.pos);
+        let n = self.inner.write(&ct)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<C: BlockCipher, S: Seek> Seek for CtrStream<C, S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = self.inner.seek(pos)?;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{aes::soft::Aes, byte_util::{base64_decode, hex_decode, hex_encode}};
+
+    use super::*;
+
+    fn cryptopals_ctr() -> Ctr<Aes> {
+        Ctr::new(Aes::new("YELLOW SUBMARINE".as_bytes()), CtrLayout::LittleEndian64 { nonce: 0 })
+    }
+
+    // S3C18
+    #[test]
+    fn ctr_challenge_18() {
+        let input = base64_decode("L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==".as_bytes());
+        let expected = "Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby ".as_bytes();
+        let actual = cryptopals_ctr().decrypt(&input);
+        assert_eq!(expected, actual);
+    }
+
+    // NIST SP 800-38A F.5.1
+    #[test]
+    fn ctr_nist_vector() {
+        let key = hex_decode("2b7e151628aed2a6abf7158809cf4f3c".as_bytes());
+        let initial = u128::from_be_bytes(hex_decode("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff".as_bytes()).try_into().unwrap());
+        let input = hex_decode("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e5130c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710".as_bytes());
+        let expected = "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee".as_bytes();
+        let ctr = Ctr::new(Aes::new(&key), CtrLayout::BigEndian128 { initial });
+        assert_eq!(expected, hex_encode(&ctr.encrypt(&input)));
+    }
+
+    #[test]
+    fn ctr_big_endian_wraps() {
+        let layout = CtrLayout::BigEndian128 { initial: u128::MAX };
+        assert_eq!([0xff; 16], layout.counter_block(0));
+        assert_eq!([0; 16], layout.counter_block(1));
+    }
+
+    #[test]
+    fn ctr_random_access() {
+        let ctr = cryptopals_ctr();
+        let input: Vec<u8> = (0..200).collect();
+        let full = ctr.encrypt(&input);
+        for (start, end) in [(0, 1), (5, 21), (15, 17), (16, 32), (33, 200), (199, 200)] {
+            assert_eq!(full[start..end], ctr.process_at(&input[start..end], start as u64));
+        }
+    }
+
+    #[test]
+    fn ctr_edit_unaligned() {
+        let ctr = cryptopals_ctr();
+        let input: Vec<u8> = (0..100).collect();
+        let ct = ctr.encrypt(&input);
+        for (offset, len) in [(0, 0), (0, 16), (3, 1), (7, 30), (15, 2), (31, 17), (90, 10), (95, 20), (100, 5)] {
+            let newtext = vec![0xaa; len];
+            let mut expected = input.clone();
+            expected.resize(expected.len().max(offset + len), 0);
+            expected[offset..offset + len].copy_from_slice(&newtext);
+            assert_eq!(ctr.encrypt(&expected), ctr.edit(&ct, offset, &newtext), "{} {}", offset, len);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "past the end")]
+    fn ctr_edit_past_end() {
+        cryptopals_ctr().edit(&[0; 10], 11, b"x");
+    }
+
+    #[test]
+    fn ctr_stream_round_trip() {
+        let input = "Stream a message through CTR in a few uneven writes.".as_bytes();
+        let mut writer = CtrStream::new(cryptopals_ctr(), Cursor::new(vec![]));
+        for chunk in input.chunks(7) {
+            writer.write_all(chunk).unwrap();
+        }
+        let ct = writer.into_inner().into_inner();
+        assert_eq!(cryptopals_ctr().encrypt(input), ct);
+
+        let mut reader = CtrStream::new(cryptopals_ctr(), Cursor::new(ct));
+        let mut actual = vec![];
+        reader.read_to_end(&mut actual).unwrap();
+        assert_eq!(input, actual);
+    }
+
+    #[test]
+    fn ctr_stream_edit_in_place() {
+        let input = "The quick brown fox jumps over the lazy dog".as_bytes();
+        let ct = cryptopals_ctr().encrypt(input);
+        let mut stream = CtrStream::new(cryptopals_ctr(), Cursor::new(ct));
+        stream.seek(SeekFrom::Start(10)).unwrap();
+        stream.write_all("green".as_bytes()).unwrap();
+        stream.seek(SeekFrom::Start(0)).unwrap();
+        let mut actual = vec![];
+        stream.read_to_end(&mut actual).unwrap();
+        assert_eq!("The quick green fox jumps over the lazy dog".as_bytes(), actual);
+    }
+}
@@ -0,0 +1,68 @@
+use super::{padding::PaddingError, BlockCipher, BlockMode, Padding};
+
+/// Electronic codebook mode, every block is encrypted independently.
+pub struct Ecb<C: BlockCipher> {
+    cipher: C,
+    padding: Padding,
+}
+
+impl<C: BlockCipher> Ecb<C> {
+    pub fn new(cipher: C, padding: Padding) -> Ecb<C> {
+        Ecb { cipher, padding }
+    }
+}
+
+impl<C: BlockCipher> BlockMode for Ecb<C> {
+    fn encrypt(&self, input: &[u8]) -> Vec<u8> {
+        let bs = self.cipher.block_size();
+        let mut res = self.padding.pad(input, bs);
+        self.cipher.encrypt_blocks(&mut res);
+        res
+    }
+
+    fn decrypt(&self, input: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        let bs = self.cipher.block_size();
+        if !input.len().is_multiple_of(bs) {
+            return Err(PaddingError::InvalidLength);
+        }
+        let mut res = input.to_vec();
+        self.cipher.decrypt_blocks(&mut res);
+        self.padding.unpad(res, bs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use crate::{aes::soft::Aes, byte_util::base64_decode, set_1::aes::decrypt_aes_128_ecb};
+    use crate::modes::ToyCipher;
+
+    use super::*;
+
+    #[test]
+    fn ecb_matches_challenge_7() {
+        let input = read_to_string("./data/set_1/ch7.txt").unwrap();
+        let input = base64_decode(input.as_bytes());
+        let key = "YELLOW SUBMARINE".as_bytes();
+        let ecb = Ecb::new(Aes::new(key), Padding::Pkcs7);
+        assert_eq!(decrypt_aes_128_ecb(&input, key), ecb.decrypt(&input));
+    }
+
+    #[test]
+    fn ecb_round_trip_aes_256() {
+        let input = "The same mode works over any AES key size.".as_bytes();
+        let ecb = Ecb::new(Aes::new(&[7u8; 32]), Padding::Pkcs7);
+        let ct = ecb.encrypt(input);
+        assert_eq!(48, ct.len());
+        assert_eq!(input, ecb.decrypt(&ct).unwrap());
+    }
+
+    #[test]
+    fn ecb_toy_cipher_repeats_blocks() {
+        let ecb = Ecb::new(ToyCipher { key: *b"toy key!" }, Padding::None);
+        let ct = ecb.encrypt("ABCDEFGHABCDEFGH".as_bytes());
+        assert_eq!(ct[..8], ct[8..]);
+        assert_eq!("ABCDEFGHABCDEFGH".as_bytes(), ecb.decrypt(&ct).unwrap());
+    }
+}
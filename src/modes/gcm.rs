@@ -0,0 +1,232 @@
+use super::{ghash::{ghash, Gf128, Ghash}, BlockCipher};
+
+/// Decryption failed because the tag didn't match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthenticationError;
+
+impl std::fmt::Display for AuthenticationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "authentication tag mismatch")
+    }
+}
+
+impl std::error::Error for AuthenticationError {}
+
+/// Galois/counter mode, as specified in NIST SP 800-38D. Data is encrypted
+/// with a 32 bit big endian counter, and the AAD and ciphertext are
+/// authenticated with GHASH under the hash key `E(K, 0^128)`.
+pub struct Gcm<C: BlockCipher> {
+    cipher: C,
+    h: Gf128,
+    tag_len: usize,
+}
+
+impl<C: BlockCipher> Gcm<C> {
+    /// GCM with a full 16 byte tag.
+    pub fn new(cipher: C) -> Gcm<C> {
+        Gcm::with_tag_len(cipher, 16)
+    }
+
+    /// GCM with the tag truncated to `tag_len` bytes. Panics unless the
+    /// tag is between 4 and 16 bytes.
+    pub fn with_tag_len(cipher: C, tag_len: usize) -> Gcm<C> {
+        assert_eq!(16, cipher.block_size(), "GCM needs a 16 byte block cipher");
+        assert!((4..=16).contains(&tag_len), "GCM tags must be 4 to 16 bytes");
+        let mut h = [0u8; 16];
+        cipher.encrypt_block(&mut h);
+        Gcm { cipher, h: Gf128::from_block(&h), tag_len }
+    }
+
+    /// The hash key `H`, exposed for attacks on GHASH.
+    pub fn hash_key(&self) -> Gf128 {
+        self.h
+    }
+
+    /// Encrypt and authenticate, returning the ciphertext and tag. The IV can
+    /// be any non-empty length, 12 bytes is used directly as the counter
+    /// and anything else is hashed into one.
+    pub fn encrypt(&self, iv: &[u8], aad: &[u8], plaintext: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let j0 = self.initial_counter(iv);
+        let ciphertext = self.gctr(inc32(j0), plaintext);
+        let tag = self.tag(j0, aad, &ciphertext);
+        (ciphertext, tag)
+    }
+
+    /// Check the tag and decrypt. Nothing is decrypted if the tag is wrong.
+    pub fn decrypt(&self, iv: &[u8], aad: &[u8], ciphertext: &[u8], tag: &[u8]) -> Result<Vec<u8>, AuthenticationError> {
+        let j0 = self.initial_counter(iv);
+        let expected = self.tag(j0, aad, ciphertext);
+        // Compare without exiting early, so the time taken doesn't leak how
+        // many tag bytes were right.
+        let diff = expected.iter().zip(tag).fold(0, |acc, (a, b)| acc | (a ^ b));
+        if tag.len() != expected.len() || diff != 0 {
+            return Err(AuthenticationError);
+        }
+        Ok(self.gctr(inc32(j0), ciphertext))
+    }
+
+    fn initial_counter(&self, iv: &[u8]) -> [u8; 16] {
+        assert!(!iv.is_empty(), "GCM IV must not be empty");
+        if iv.len() == 12 {
+            let mut j0 = [0u8; 16];
+            j0[..12].copy_from_slice(iv);
+            j0[15] = 1;
+            j0
+        } else {
+            let mut g = Ghash::new(self.h);
+            g.update(iv);
+            let mut lengths = [0u8; 16];
+            lengths[8..].copy_from_slice(&(iv.len() as u64 * 8).to_be_bytes());
+            g.update(&lengths);
+            g.finalize()
+        }
+    }
+
+    fn tag(&self, j0: [u8; 16], aad: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+        let s = ghash(self.h, aad, ciphertext);
+        let mut tag = self.gctr(j0, &s);
+        tag.truncate(self.tag_len);
+        tag
+    }
+
+    /// CTR mode where only the last 32 bits of the counter block increment.
+    fn gctr(&self, mut counter: [u8; 16], input: &[u8]) -> Vec<u8> {
+        let mut res = Vec::with_capacity(input.len());
+        for block in input.chunks(16) {
+            let mut keystream = counter;
+            self.cipher.encrypt_block(&mut keystream);
+            res.extend(block.iter().zip(&keystream).map(|(a, b)| a ^ b));
+            counter = inc32(counter);
+        }
+        res
+    }
+}
+
+/// Increment the last 32 bits of a block as a big endian integer, wrapping.
+fn inc32(mut block: [u8; 16]) -> [u8; 16] {
+    let ctr = u32::from_be_bytes([block[12], block[13], block[14], block[15]]).wrapping_add(1);
+    block[12..].copy_from_slice(&ctr.to_be_bytes());
+    block
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes::soft::Aes, byte_util::{hex_decode, hex_encode}};
+
+    use super::*;
+
+    const KEY: &str = "feffe9928665731c6d6a8f9467308308";
+    const PLAINTEXT: &str = "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255";
+    const AAD: &str = "feedfacedeadbeeffeedfacedeadbeefabaddad2";
+
+    fn check_vector(key: &str, iv: &str, aad: &str, plaintext: &str, ciphertext: &str, tag: &str) {
+        let gcm = Gcm::new(Aes::new(&hex_decode(key.as_bytes())));
+        let iv = hex_decode(iv.as_bytes());
+        let aad = hex_decode(aad.as_bytes());
+        let plaintext = hex_decode(plaintext.as_bytes());
+        let (actual_ct, actual_tag) = gcm.encrypt(&iv, &aad, &plaintext);
+        assert_eq!(ciphertext.as_bytes(), hex_encode(&actual_ct));
+        assert_eq!(tag.as_bytes(), hex_encode(&actual_tag));
+        assert_eq!(plaintext, gcm.decrypt(&iv, &aad, &actual_ct, &actual_tag).unwrap());
+    }
+
+    // The test cases from "The Galois/Counter Mode of Operation", McGrew and
+    // Viega, appendix B.
+    #[test]
+    fn gcm_test_case_1() {
+        check_vector(
+            "00000000000000000000000000000000",
+            "000000000000000000000000",
+            "",
+            "",
+            "",
+            "58e2fccefa7e3061367f1d57a4e7455a",
+        );
+    }
+
+    #[test]
+    fn gcm_test_case_2() {
+        check_vector(
+            "00000000000000000000000000000000",
+            "000000000000000000000000",
+            "",
+            "00000000000000000000000000000000",
+            "0388dace60b6a392f328c2b971b2fe78",
+            "ab6e47d42cec13bdf53a67b21257bddf",
+        );
+    }
+
+    #[test]
+    fn gcm_test_case_3() {
+        check_vector(
+            KEY,
+            "cafebabefacedbaddecaf888",
+            "",
+            PLAINTEXT,
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091473f5985",
+            "4d5c2af327cd64a62cf35abd2ba6fab4",
+        );
+    }
+
+    #[test]
+    fn gcm_test_case_4() {
+        check_vector(
+            KEY,
+            "cafebabefacedbaddecaf888",
+            AAD,
+            &PLAINTEXT[..120],
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091",
+            "5bc94fbc3221a5db94fae95ae7121a47",
+        );
+    }
+
+    // A 64 bit IV, which gets hashed into the initial counter.
+    #[test]
+    fn gcm_test_case_5() {
+        check_vector(
+            KEY,
+            "cafebabefacedbad",
+            AAD,
+            &PLAINTEXT[..120],
+            "61353b4c2806934a777ff51fa22a4755699b2a714fcdc6f83766e5f97b6c742373806900e49f24b22b097544d4896b424989b5e1ebac0f07c23f4598",
+            "3612d2e79e3b0785561be14aaca2fccb",
+        );
+    }
+
+    // A 60 byte IV.
+    #[test]
+    fn gcm_test_case_6() {
+        check_vector(
+            KEY,
+            "9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728c3c0c95156809539fcf0e2429a6b525416aedbf5a0de6a57a637b39b",
+            AAD,
+            &PLAINTEXT[..120],
+            "8ce24998625615b603a033aca13fb894be9112a5c3a211a8ba262a3cca7e2ca701e4a9a4fba43c90ccdcb281d48c7c6fd62875d2aca417034c34aee5",
+            "619cc5aefffe0bfa462af43c1699d050",
+        );
+    }
+
+    #[test]
+    fn gcm_truncated_tag() {
+        let key = hex_decode(KEY.as_bytes());
+        let iv = hex_decode("cafebabefacedbaddecaf888".as_bytes());
+        let gcm = Gcm::with_tag_len(Aes::new(&key), 8);
+        let (ct, tag) = gcm.encrypt(&iv, "header".as_bytes(), "message".as_bytes());
+        assert_eq!(8, tag.len());
+        // A truncated tag is the prefix of the full one.
+        let (_, full_tag) = Gcm::new(Aes::new(&key)).encrypt(&iv, "header".as_bytes(), "message".as_bytes());
+        assert_eq!(full_tag[..8], tag);
+        assert_eq!("message".as_bytes(), gcm.decrypt(&iv, "header".as_bytes(), &ct, &tag).unwrap());
+    }
+
+    #[test]
+    fn gcm_rejects_tampering() {
+        let gcm = Gcm::new(Aes::new("YELLOW SUBMARINE".as_bytes()));
+        let iv = [9u8; 12];
+        let (mut ct, tag) = gcm.encrypt(&iv, "aad".as_bytes(), "attack at dawn".as_bytes());
+        assert_eq!(Err(AuthenticationError), gcm.decrypt(&iv, "aaD".as_bytes(), &ct, &tag));
+        assert_eq!(Err(AuthenticationError), gcm.decrypt(&iv, "aad".as_bytes(), &ct, &tag[..15]));
+        ct[0] ^= 1;
+        assert_eq!(Err(AuthenticationError), gcm.decrypt(&iv, "aad".as_bytes(), &ct, &tag));
+    }
+}
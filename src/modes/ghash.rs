@@ -0,0 +1,135 @@
+//! Arithmetic in GF(2^128) as used by GCM, and the GHASH function built on
+//! it.
+//!
+//! GCM uses a bit reflected convention. The first bit of a block is the
+//! coefficient of x^0 and the field is reduced by x^128 + x^7 + x^2 + x + 1.
+//! Reading a block as a big endian `u128` puts x^0 in the most significant
+//! bit, so multiplying by x is a right shift.
+
+use std::ops::{Add, Mul};
+
+/// An element of GF(2^128) in the GCM bit order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Gf128(pub u128);
+
+/// The reduction polynomial without its x^128 term, in GCM bit order.
+const R: u128 = 0xe1 << 120;
+
+impl Gf128 {
+    pub fn from_block(block: &[u8]) -> Gf128 {
+        let mut bytes = [0u8; 16];
+        bytes[..block.len()].copy_from_slice(block);
+        Gf128(u128::from_be_bytes(bytes))
+    }
+
+    pub fn to_block(self) -> [u8; 16] {
+        self.0.to_be_bytes()
+    }
+}
+
+/// Addition in a binary field is XOR.
+impl Add for Gf128 {
+    type Output = Gf128;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn add(self, rhs: Gf128) -> Gf128 {
+        Gf128(self.0 ^ rhs.0)
+    }
+}
+
+/// Shift and add multiplication, algorithm 1 of the GCM spec.
+impl Mul for Gf128 {
+    type Output = Gf128;
+
+    fn mul(self, rhs: Gf128) -> Gf128 {
+        let mut z = 0u128;
+        let mut v = rhs.0;
+        for i in (0..128).rev() {
+            if (self.0 >> i) & 1 == 1 {
+                z ^= v;
+            }
+            v = if v & 1 == 0 { v >> 1 } else { (v >> 1) ^ R };
+        }
+        Gf128(z)
+    }
+}
+
+/// Incremental GHASH under the hash key `h`. Blocks are absorbed as
+/// `y = (y + x) * h`, and a short final block is padded with zeros.
+#[derive(Clone, Debug)]
+pub struct Ghash {
+    h: Gf128,
+    y: Gf128,
+}
+
+impl Ghash {
+    pub fn new(h: Gf128) -> Ghash {
+        Ghash { h, y: Gf128::default() }
+    }
+
+    /// Absorb data, zero padding it to a whole number of blocks.
+    pub fn update(&mut self, data: &[u8]) {
+        for block in data.chunks(16) {
+            self.y = (self.y + Gf128::from_block(block)) * self.h;
+        }
+    }
+
+    pub fn finalize(self) -> [u8; 16] {
+        self.y.to_block()
+    }
+}
+
+/// GHASH over the AAD and ciphertext as GCM uses it, each zero padded and
+/// followed by a block holding both of their lengths in bits.
+pub fn ghash(h: Gf128, aad: &[u8], ciphertext: &[u8]) -> [u8; 16] {
+    let mut g = Ghash::new(h);
+    g.update(aad);
+    g.update(ciphertext);
+    let mut lengths = [0u8; 16];
+    lengths[..8].copy_from_slice(&(aad.len() as u64 * 8).to_be_bytes());
+    lengths[8..].copy_from_slice(&(ciphertext.len() as u64 * 8).to_be_bytes());
+    g.update(&lengths);
+    g.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::byte_util::{hex_decode, hex_encode};
+
+    use super::*;
+
+    #[test]
+    fn one_is_identity() {
+        // x^0 is the top bit in GCM order.
+        let one = Gf128(1 << 127);
+        let a = Gf128(0x66e94bd4ef8a2c3b884cfa59ca342b2e);
+        assert_eq!(a, a * one);
+        assert_eq!(a, one * a);
+    }
+
+    #[test]
+    fn multiply_is_commutative_and_distributive() {
+        let a = Gf128(0x0388dace60b6a392f328c2b971b2fe78);
+        let b = Gf128(0x66e94bd4ef8a2c3b884cfa59ca342b2e);
+        let c = Gf128(0xfeffe9928665731c6d6a8f9467308308);
+        assert_eq!(a * b, b * a);
+        assert_eq!(a * (b + c), a * b + a * c);
+    }
+
+    #[test]
+    fn x_to_the_128_reduces() {
+        // x^127 * x = x^128 = x^7 + x^2 + x + 1
+        let x127 = Gf128(1);
+        let x = Gf128(1 << 126);
+        assert_eq!(Gf128(R), x127 * x);
+    }
+
+    // GCM spec test case 2, GHASH(H, {}, C).
+    #[test]
+    fn ghash_test_case_2() {
+        let h = Gf128::from_block(&hex_decode("66e94bd4ef8a2c3b884cfa59ca342b2e".as_bytes()));
+        let c = hex_decode("0388dace60b6a392f328c2b971b2fe78".as_bytes());
+        let actual = ghash(h, &[], &c);
+        assert_eq!("f38cbb1ad69223dcc3457ae5b6b0f885".as_bytes(), hex_encode(&actual));
+    }
+}
@@ -0,0 +1,287 @@
+//! Block cipher modes of operation, written against a [`BlockCipher`] so the
+//! same mode code runs over AES of any key size, or a toy cipher in tests.
+
+use padding::{
+    ansi_x923_padding, ansi_x923_unpad, iso_10126_padding, iso_10126_unpad, iso_7816_padding, iso_7816_unpad,
+    pkcs7_padding, pkcs7_unpad, zero_padding, zero_unpad,
+};
+
+pub mod cbc;
+pub mod cfb;
+pub mod ctr;
+pub mod ecb;
+pub mod gcm;
+pub mod ghash;
+pub mod ofb;
+pub mod padding;
+pub mod pcbc;
+
+pub use cbc::{Cbc, CbcCts, Cts};
+pub use cfb::Cfb;
+pub use ctr::{Ctr, CtrLayout, CtrStream};
+pub use ecb::Ecb;
+pub use gcm::{AuthenticationError, Gcm};
+pub use ofb::Ofb;
+pub use padding::PaddingError;
+pub use pcbc::Pcbc;
+
+/// A keyed block cipher that transforms one block at a time in place.
+pub trait BlockCipher {
+    /// The block size in bytes.
+    fn block_size(&self) -> usize;
+
+    /// Encrypt a single block in place. `block` is exactly `block_size` bytes.
+    fn encrypt_block(&self, block: &mut [u8]);
+
+    /// Decrypt a single block in place. `block` is exactly `block_size` bytes.
+    fn decrypt_block(&self, block: &mut [u8]);
+
+    /// Encrypt every block of a block aligned buffer in place. Ciphers that
+    /// can do a whole buffer faster than a block at a time override this.
+    fn encrypt_blocks(&self, data: &mut [u8]) {
+        for block in data.chunks_mut(self.block_size()) {
+            self.encrypt_block(block);
+        }
+    }
+
+    /// Decrypt every block of a block aligned buffer in place.
+    fn decrypt_blocks(&self, data: &mut [u8]) {
+        for block in data.chunks_mut(self.block_size()) {
+            self.decrypt_block(block);
+        }
+    }
+}
+
+/// A mode of operation that turns a block cipher into a cipher over
+/// messages of any length, by padding them out to whole blocks.
+pub trait BlockMode {
+    fn encrypt(&self, input: &[u8]) -> Vec<u8>;
+
+    /// Decrypt and strip the padding, failing if the padding is malformed.
+    fn decrypt(&self, input: &[u8]) -> Result<Vec<u8>, PaddingError>;
+}
+
+/// A mode of operation that turns a block cipher into a stream cipher.
+/// There's no padding, so the ciphertext is as long as the message and
+/// decrypting can't fail.
+pub trait StreamMode {
+    fn encrypt(&self, input: &[u8]) -> Vec<u8>;
+
+    fn decrypt(&self, input: &[u8]) -> Vec<u8>;
+}
+
+/// How a mode fills out the last block of a message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Padding {
+    /// No padding, input must already be a multiple of the block size.
+    None,
+    /// PKCS#7, pad with n bytes of value n. Always adds at least one byte.
+    Pkcs7,
+    /// ANSI X9.23, pad with zeros and a final byte of n.
+    AnsiX923,
+    /// ISO 10126, pad with random bytes and a final byte of n.
+    Iso10126,
+    /// ISO/IEC 7816-4, pad with 0x80 and then zeros.
+    Iso7816,
+    /// Pad with zeros, adding nothing to aligned input. Not reversible for
+    /// data ending in zeros.
+    Zero,
+}
+
+impl Padding {
+    pub fn pad(&self, input: &[u8], block_size: usize) -> Vec<u8> {
+        match self {
+            Padding::None => {
+                assert!(input.len().is_multiple_of(block_size), "input is not block aligned");
+                input.to_vec()
+            }
+            Padding::Pkcs7 => pkcs7_padding(input, block_size),
+            Padding::AnsiX923 => ansi_x923_padding(input, block_size),
+            Padding::Iso10126 => iso_10126_padding(input, block_size),
+            Padding::Iso7816 => iso_7816_padding(input, block_size),
+            Padding::Zero => zero_padding(input, block_size),
+        }
+    }
+
+    /// Strip the padding from a decrypted message, checking it is well
+    /// formed.
+    pub fn unpad(&self, input: Vec<u8>, block_size: usize) -> Result<Vec<u8>, PaddingError> {
+        match self {
+            Padding::None => Ok(input),
+            Padding::Pkcs7 => pkcs7_unpad(&input, block_size),
+            Padding::AnsiX923 => ansi_x923_unpad(&input, block_size),
+            Padding::Iso10126 => iso_10126_unpad(&input, block_size),
+            Padding::Iso7816 => iso_7816_unpad(&input, block_size),
+            Padding::Zero => zero_unpad(&input, block_size),
+        }
+    }
+}
+
+impl BlockCipher for crate::aes::soft::Aes {
+    fn block_size(&self) -> usize {
+        16
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let block: &mut [u8; 16] = block.try_into().unwrap();
+        crate::aes::soft::Aes::encrypt_block(self, block);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let block: &mut [u8; 16] = block.try_into().unwrap();
+        crate::aes::soft::Aes::decrypt_block(self, block);
+    }
+}
+
+impl BlockCipher for crate::aes::Aes {
+    fn block_size(&self) -> usize {
+        16
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        self.encrypt_blocks(block);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        self.decrypt_blocks(block);
+    }
+
+    fn encrypt_blocks(&self, data: &mut [u8]) {
+        crate::aes::Aes::encrypt_blocks(self, data);
+    }
+
+    fn decrypt_blocks(&self, data: &mut [u8]) {
+        crate::aes::Aes::decrypt_blocks(self, data);
+    }
+}
+
+impl BlockCipher for crate::aes::Aes128 {
+    fn block_size(&self) -> usize {
+        16
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        self.encrypt_blocks(block);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        self.decrypt_blocks(block);
+    }
+
+    fn encrypt_blocks(&self, data: &mut [u8]) {
+        crate::aes::Aes::encrypt_blocks(self, data);
+    }
+
+    fn decrypt_blocks(&self, data: &mut [u8]) {
+        crate::aes::Aes::decrypt_blocks(self, data);
+    }
+}
+
+impl BlockCipher for crate::aes::FastAes {
+    fn block_size(&self) -> usize {
+        16
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let block: &mut [u8; 16] = block.try_into().unwrap();
+        crate::aes::FastAes::encrypt_block(self, block);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let block: &mut [u8; 16] = block.try_into().unwrap();
+        crate::aes::FastAes::decrypt_block(self, block);
+    }
+}
+
+/// A deliberately weak 8 byte block cipher for testing that the modes don't
+/// depend on AES or its block size. Each block is XORed with the key and
+/// rotated by one byte.
+#[cfg(test)]
+pub(crate) struct ToyCipher {
+    pub key: [u8; 8],
+}
+
+#[cfg(test)]
+impl BlockCipher for ToyCipher {
+    fn block_size(&self) -> usize {
+        8
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        for (b, k) in block.iter_mut().zip(self.key) {
+            *b ^= k;
+        }
+        block.rotate_left(1);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        block.rotate_right(1);
+        for (b, k) in block.iter_mut().zip(self.key) {
+            *b ^= k;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes::soft::Aes, set_1::aes::detect_aes_128_ecb};
+
+    use super::*;
+
+    #[test]
+    fn only_ecb_repeats_blocks() {
+        let key = "YELLOW SUBMARINE".as_bytes();
+        let iv = [3u8; 16];
+        let input = [b'A'; 64];
+        let modes: Vec<(&str, Box<dyn BlockMode>)> = vec![
+            ("ecb", Box::new(Ecb::new(Aes::new(key), Padding::Pkcs7))),
+            ("cbc", Box::new(Cbc::new(Aes::new(key), &iv, Padding::Pkcs7))),
+            ("pcbc", Box::new(Pcbc::new(Aes::new(key), &iv, Padding::Pkcs7))),
+        ];
+        for (name, mode) in modes {
+            let ct = mode.encrypt(&input);
+            assert_eq!(name == "ecb", detect_aes_128_ecb(&ct), "{}", name);
+            assert_eq!(input.as_slice(), mode.decrypt(&ct).unwrap(), "{}", name);
+        }
+        let modes: Vec<(&str, Box<dyn StreamMode>)> = vec![
+            ("cfb8", Box::new(Cfb::new(Aes::new(key), &iv, 8))),
+            ("cfb128", Box::new(Cfb::new(Aes::new(key), &iv, 128))),
+            ("ofb", Box::new(Ofb::new(Aes::new(key), &iv))),
+            ("ctr", Box::new(Ctr::new(Aes::new(key), CtrLayout::LittleEndian64 { nonce: 0 }))),
+        ];
+        for (name, mode) in modes {
+            let ct = mode.encrypt(&input);
+            assert!(!detect_aes_128_ecb(&ct), "{}", name);
+            assert_eq!(input.as_slice(), mode.decrypt(&ct), "{}", name);
+        }
+    }
+
+    #[test]
+    fn block_modes_reject_unaligned() {
+        let key = "YELLOW SUBMARINE".as_bytes();
+        let iv = [3u8; 16];
+        let modes: Vec<Box<dyn BlockMode>> = vec![
+            Box::new(Ecb::new(Aes::new(key), Padding::None)),
+            Box::new(Cbc::new(Aes::new(key), &iv, Padding::Pkcs7)),
+            Box::new(Pcbc::new(Aes::new(key), &iv, Padding::Pkcs7)),
+        ];
+        for mode in modes {
+            assert_eq!(Err(PaddingError::InvalidLength), mode.decrypt(&[0u8; 15]));
+            assert_eq!(Err(PaddingError::InvalidLength), mode.decrypt(&[0u8; 33]));
+        }
+    }
+
+    #[test]
+    fn pkcs7_pad_happy() {
+        let actual = Padding::Pkcs7.pad("YELLOW SUBMARINE".as_bytes(), 20);
+        assert_eq!("YELLOW SUBMARINE\x04\x04\x04\x04".as_bytes(), actual);
+    }
+
+    #[test]
+    fn pkcs7_pad_aligned() {
+        let actual = Padding::Pkcs7.pad("YELLOW SUBMARINE".as_bytes(), 16);
+        assert_eq!(32, actual.len());
+        assert_eq!(vec![16u8; 16], actual[16..]);
+        assert_eq!("YELLOW SUBMARINE".as_bytes(), Padding::Pkcs7.unpad(actual, 16).unwrap());
+    }
+}
@@ -0,0 +1,68 @@
+use super::{BlockCipher, StreamMode};
+
+/// Output feedback mode. The IV is encrypted over and over to make a
+/// keystream which is XORed with the data. The keystream never depends on
+/// the data, so like CTR this is a stream cipher and needs no padding.
+pub struct Ofb<C: BlockCipher> {
+    cipher: C,
+    iv: Vec<u8>,
+}
+
+impl<C: BlockCipher> Ofb<C> {
+    /// Panics if the IV is not exactly one block long.
+    pub fn new(cipher: C, iv: &[u8]) -> Ofb<C> {
+        assert_eq!(cipher.block_size(), iv.len(), "IV must be one block long");
+        Ofb { cipher, iv: iv.to_vec() }
+    }
+
+    fn process(&self, input: &[u8]) -> Vec<u8> {
+        let mut keystream = self.iv.clone();
+        let mut res = Vec::with_capacity(input.len());
+        for block in input.chunks(self.cipher.block_size()) {
+            self.cipher.encrypt_block(&mut keystream);
+            res.extend(block.iter().zip(&keystream).map(|(a, b)| a ^ b));
+        }
+        res
+    }
+}
+
+impl<C: BlockCipher> StreamMode for Ofb<C> {
+    fn encrypt(&self, input: &[u8]) -> Vec<u8> {
+        self.process(input)
+    }
+
+    fn decrypt(&self, input: &[u8]) -> Vec<u8> {
+        self.process(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes::soft::Aes, byte_util::{hex_decode, hex_encode}};
+
+    use super::*;
+
+    // NIST SP 800-38A F.4.1
+    #[test]
+    fn ofb_nist_vector() {
+        let key = hex_decode("2b7e151628aed2a6abf7158809cf4f3c".as_bytes());
+        let iv = hex_decode("000102030405060708090a0b0c0d0e0f".as_bytes());
+        let input = hex_decode("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e5130c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710".as_bytes());
+        let expected = "3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed8259740051e9c5fecf64344f7a82260edcc304c6528f659c77866a510d9c1d6ae5e".as_bytes();
+        let ofb = Ofb::new(Aes::new(&key), &iv);
+        let actual = ofb.encrypt(&input);
+        assert_eq!(expected, hex_encode(&actual));
+        assert_eq!(input, ofb.decrypt(&actual));
+    }
+
+    #[test]
+    fn ofb_bit_flip_is_local() {
+        let ofb = Ofb::new(Aes::new("YELLOW SUBMARINE".as_bytes()), &[0u8; 16]);
+        let input = "Flipping a ciphertext bit flips only that plaintext bit".as_bytes();
+        let mut ct = ofb.encrypt(input);
+        ct[20] ^= 0x20;
+        let mut expected = input.to_vec();
+        expected[20] ^= 0x20;
+        assert_eq!(expected, ofb.decrypt(&ct));
+    }
+}
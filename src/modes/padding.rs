@@ -0,0 +1,263 @@
+//! Padding schemes for block modes, filling a message out to a whole
+//! number of blocks and checking the fill is well formed on the way back.
+
+use rand::RngCore;
+
+/// Pad the input to a multiple of the block length using PKCS#7. Each pad
+/// byte is the number of bytes added, and a full block of padding is added
+/// when the input is already aligned, so the padding can always be removed.
+pub fn pkcs7_padding(input: &[u8], block_length: usize) -> Vec<u8> {
+    assert!(block_length > 0 && block_length < 256, "PKCS#7 block length must be 1 to 255");
+    let n = block_length - input.len() % block_length;
+    let mut res = Vec::with_capacity(input.len() + n);
+    res.extend_from_slice(input);
+    res.resize(input.len() + n, n as u8);
+
+    res
+}
+
+/// Check that the input ends in well formed PKCS#7 padding, without
+/// copying it. This is the whole of what a padding oracle leaks.
+pub fn pkcs7_valid(input: &[u8], block_length: usize) -> bool {
+    match split_length_padding(input, block_length) {
+        Ok((_, pad)) => pad.iter().all(|&b| b as usize == pad.len()),
+        Err(_) => false,
+    }
+}
+
+/// The ways padding can be malformed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaddingError {
+    /// The input is empty or not a multiple of the block length.
+    InvalidLength,
+    /// The last byte is zero or larger than the block length.
+    InvalidPadLength(u8),
+    /// One of the pad bytes isn't what the padding scheme requires.
+    InvalidPadByte,
+}
+
+impl std::fmt::Display for PaddingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaddingError::InvalidLength => write!(f, "input length is not a positive multiple of the block length"),
+            PaddingError::InvalidPadLength(n) => write!(f, "invalid pad length {}", n),
+            PaddingError::InvalidPadByte => write!(f, "pad bytes are malformed"),
+        }
+    }
+}
+
+impl std::error::Error for PaddingError {}
+
+/// Strip PKCS#7 padding, checking that it is well formed. Every one of the
+/// last n bytes must be n, where n is between 1 and the block length.
+pub fn pkcs7_unpad(input: &[u8], block_length: usize) -> Result<Vec<u8>, PaddingError> {
+    let (data, pad) = split_length_padding(input, block_length)?;
+    if pad.iter().any(|&b| b as usize != pad.len()) {
+        return Err(PaddingError::InvalidPadByte);
+    }
+    Ok(data.to_vec())
+}
+
+/// Pad the input using ANSI X9.23, zero bytes followed by a final byte
+/// giving the number of bytes added. Like PKCS#7 it always adds at least one
+/// byte.
+pub fn ansi_x923_padding(input: &[u8], block_length: usize) -> Vec<u8> {
+    let mut res = pkcs7_padding(input, block_length);
+    let n = res.len() - input.len();
+    res[input.len()..input.len() + n - 1].fill(0);
+    res
+}
+
+/// Strip ANSI X9.23 padding, checking the pad bytes before the length are
+/// all zero.
+pub fn ansi_x923_unpad(input: &[u8], block_length: usize) -> Result<Vec<u8>, PaddingError> {
+    let (data, pad) = split_length_padding(input, block_length)?;
+    if pad[..pad.len() - 1].iter().any(|&b| b != 0) {
+        return Err(PaddingError::InvalidPadByte);
+    }
+    Ok(data.to_vec())
+}
+
+/// Pad the input using ISO 10126, random bytes followed by a final byte
+/// giving the number of bytes added.
+pub fn iso_10126_padding(input: &[u8], block_length: usize) -> Vec<u8> {
+    iso_10126_padding_with_rng(input, block_length, &mut rand::thread_rng())
+}
+
+/// [`iso_10126_padding`] with the random bytes taken from `rng`, so the
+/// output can be reproduced.
+pub fn iso_10126_padding_with_rng<R: RngCore>(input: &[u8], block_length: usize, rng: &mut R) -> Vec<u8> {
+    let mut res = pkcs7_padding(input, block_length);
+    let n = res.len() - input.len();
+    rng.fill_bytes(&mut res[input.len()..input.len() + n - 1]);
+    res
+}
+
+/// Strip ISO 10126 padding. Only the length byte can be checked, the rest
+/// of the padding is random.
+pub fn iso_10126_unpad(input: &[u8], block_length: usize) -> Result<Vec<u8>, PaddingError> {
+    let (data, _) = split_length_padding(input, block_length)?;
+    Ok(data.to_vec())
+}
+
+/// Pad the input using ISO/IEC 7816-4, a single 0x80 byte followed by as
+/// many zero bytes as needed. Always adds at least one byte.
+pub fn iso_7816_padding(input: &[u8], block_length: usize) -> Vec<u8> {
+    let mut res = pkcs7_padding(input, block_length);
+    res[input.len()] = 0x80;
+    res[input.len() + 1..].fill(0);
+    res
+}
+
+/// Strip ISO/IEC 7816-4 padding. Trailing zeros are skipped, and the byte
+/// before them must be the 0x80 marker, all within the last block.
+pub fn iso_7816_unpad(input: &[u8], block_length: usize) -> Result<Vec<u8>, PaddingError> {
+    check_padded_length(input, block_length)?;
+    let last_block = &input[input.len() - block_length..];
+    match last_block.iter().rposition(|&b| b != 0) {
+        Some(i) if last_block[i] == 0x80 => Ok(input[..input.len() - block_length + i].to_vec()),
+        _ => Err(PaddingError::InvalidPadByte),
+    }
+}
+
+/// Pad the input with zero bytes, adding nothing if it is already aligned.
+/// This can't be reversed for data that ends in zero bytes.
+pub fn zero_padding(input: &[u8], block_length: usize) -> Vec<u8> {
+    let mut res = input.to_vec();
+    res.resize(input.len().next_multiple_of(block_length), 0);
+    res
+}
+
+/// Strip zero padding, removing any trailing zero bytes in the last block
+/// up to one less than the block length.
+pub fn zero_unpad(input: &[u8], block_length: usize) -> Result<Vec<u8>, PaddingError> {
+    check_padded_length(input, block_length)?;
+    let n = input.iter().rev().take(block_length - 1).take_while(|&&b| b == 0).count();
+    Ok(input[..input.len() - n].to_vec())
+}
+
+fn check_padded_length(input: &[u8], block_length: usize) -> Result<(), PaddingError> {
+    if input.is_empty() || !input.len().is_multiple_of(block_length) {
+        return Err(PaddingError::InvalidLength);
+    }
+    Ok(())
+}
+
+/// Split off padding whose length is given by the last byte, as in PKCS#7,
+/// ANSI X9.23 and ISO 10126, checking that length is between 1 and the
+/// block length.
+fn split_length_padding(input: &[u8], block_length: usize) -> Result<(&[u8], &[u8]), PaddingError> {
+    check_padded_length(input, block_length)?;
+    let n = input[input.len() - 1];
+    if n == 0 || n as usize > block_length {
+        return Err(PaddingError::InvalidPadLength(n));
+    }
+    Ok(input.split_at(input.len() - n as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    #[test]
+    fn pkcs7_padding_happy() {
+        let input = "YELLOW SUBMARINE".as_bytes();
+        let expected = "YELLOW SUBMARINE\x04\x04\x04\x04".as_bytes();
+        let actual = pkcs7_padding(input, 20);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn pkcs7_padding_aligned() {
+        let input = "YELLOW SUBMARINE".as_bytes();
+        let actual = pkcs7_padding(input, 16);
+        assert_eq!(32, actual.len());
+        assert_eq!(input, &actual[..16]);
+        assert_eq!(vec![16u8; 16], actual[16..]);
+    }
+
+    #[test]
+    fn pkcs7_unpad_round_trip() {
+        for n in 0..40 {
+            let input = vec![b'A'; n];
+            assert_eq!(input, pkcs7_unpad(&pkcs7_padding(&input, 16), 16).unwrap());
+        }
+    }
+
+    #[test]
+    fn pkcs7_valid_strict() {
+        assert!(pkcs7_valid("ICE ICE BABY\x04\x04\x04\x04".as_bytes(), 16));
+        assert!(pkcs7_valid(&[16u8; 16], 16));
+        assert!(!pkcs7_valid("ICE ICE BABY\x05\x05\x05\x05".as_bytes(), 16));
+        assert!(!pkcs7_valid("ICE ICE BABY\x01\x02\x03\x04".as_bytes(), 16));
+        assert!(!pkcs7_valid("ICE ICE BABY\x03\x03\x03\x00".as_bytes(), 16));
+        assert!(!pkcs7_valid(&[17u8; 16], 16));
+        assert!(!pkcs7_valid("ICE ICE BABY\x01".as_bytes(), 16));
+    }
+
+    // S2C15
+    #[test]
+    fn pkcs7_unpad_happy() {
+        let input = "ICE ICE BABY\x04\x04\x04\x04".as_bytes();
+        assert_eq!("ICE ICE BABY".as_bytes(), pkcs7_unpad(input, 16).unwrap());
+    }
+
+    #[test]
+    fn pkcs7_unpad_invalid() {
+        let wrong_count = "ICE ICE BABY\x05\x05\x05\x05".as_bytes();
+        assert_eq!(Err(PaddingError::InvalidPadByte), pkcs7_unpad(wrong_count, 16));
+        let mixed = "ICE ICE BABY\x01\x02\x03\x04".as_bytes();
+        assert_eq!(Err(PaddingError::InvalidPadByte), pkcs7_unpad(mixed, 16));
+        let zero = "ICE ICE BABY\x00\x00\x00\x00".as_bytes();
+        assert_eq!(Err(PaddingError::InvalidPadLength(0)), pkcs7_unpad(zero, 16));
+        let too_long = "ICE ICE BABY\x11\x11\x11\x11".as_bytes();
+        assert_eq!(Err(PaddingError::InvalidPadLength(17)), pkcs7_unpad(too_long, 16));
+        assert_eq!(Err(PaddingError::InvalidLength), pkcs7_unpad("ICE ICE BABY\x01".as_bytes(), 16));
+        assert_eq!(Err(PaddingError::InvalidLength), pkcs7_unpad(&[], 16));
+    }
+
+    #[test]
+    fn ansi_x923_padding_happy() {
+        let actual = ansi_x923_padding("ICE ICE BABY".as_bytes(), 16);
+        assert_eq!("ICE ICE BABY\x00\x00\x00\x04".as_bytes(), actual);
+        assert_eq!("ICE ICE BABY".as_bytes(), ansi_x923_unpad(&actual, 16).unwrap());
+        let bad = "ICE ICE BABY\x00\x01\x00\x04".as_bytes();
+        assert_eq!(Err(PaddingError::InvalidPadByte), ansi_x923_unpad(bad, 16));
+    }
+
+    #[test]
+    fn iso_10126_padding_happy() {
+        let actual = iso_10126_padding("ICE ICE BABY".as_bytes(), 16);
+        assert_eq!(16, actual.len());
+        assert_eq!(4, actual[15]);
+        assert_eq!("ICE ICE BABY".as_bytes(), iso_10126_unpad(&actual, 16).unwrap());
+        let bad = "ICE ICE BABY\x00\x00\x00\x14".as_bytes();
+        assert_eq!(Err(PaddingError::InvalidPadLength(20)), iso_10126_unpad(bad, 16));
+        let seeded = |seed| iso_10126_padding_with_rng("ICE ICE BABY".as_bytes(), 16, &mut StdRng::seed_from_u64(seed));
+        assert_eq!(seeded(1), seeded(1));
+    }
+
+    #[test]
+    fn iso_7816_padding_happy() {
+        let actual = iso_7816_padding("ICE ICE BABY".as_bytes(), 16);
+        assert_eq!(b"ICE ICE BABY\x80\x00\x00\x00".as_slice(), actual);
+        assert_eq!("ICE ICE BABY".as_bytes(), iso_7816_unpad(&actual, 16).unwrap());
+        let aligned = iso_7816_padding("YELLOW SUBMARINE".as_bytes(), 16);
+        assert_eq!(32, aligned.len());
+        assert_eq!("YELLOW SUBMARINE".as_bytes(), iso_7816_unpad(&aligned, 16).unwrap());
+        let bad = b"ICE ICE BABY\x81\x00\x00\x00";
+        assert_eq!(Err(PaddingError::InvalidPadByte), iso_7816_unpad(bad, 16));
+        assert_eq!(Err(PaddingError::InvalidPadByte), iso_7816_unpad(&[0u8; 16], 16));
+    }
+
+    #[test]
+    fn zero_padding_happy() {
+        let actual = zero_padding("ICE ICE BABY".as_bytes(), 16);
+        assert_eq!("ICE ICE BABY\x00\x00\x00\x00".as_bytes(), actual);
+        assert_eq!("ICE ICE BABY".as_bytes(), zero_unpad(&actual, 16).unwrap());
+        assert_eq!(16, zero_padding("YELLOW SUBMARINE".as_bytes(), 16).len());
+        assert_eq!(Err(PaddingError::InvalidLength), zero_unpad("ICE".as_bytes(), 16));
+    }
+}
@@ -0,0 +1,95 @@
+use super::{padding::PaddingError, BlockCipher, BlockMode, Padding};
+
+/// Propagating cipher block chaining mode. Each plaintext block is XORed
+/// with both the previous plaintext and ciphertext blocks (or the IV for
+/// the first block) before being encrypted, so a corrupted ciphertext block
+/// garbles every block after it.
+pub struct Pcbc<C: BlockCipher> {
+    cipher: C,
+    iv: Vec<u8>,
+    padding: Padding,
+}
+
+impl<C: BlockCipher> Pcbc<C> {
+    /// Panics if the IV is not exactly one block long.
+    pub fn new(cipher: C, iv: &[u8], padding: Padding) -> Pcbc<C> {
+        assert_eq!(cipher.block_size(), iv.len(), "IV must be one block long");
+        Pcbc { cipher, iv: iv.to_vec(), padding }
+    }
+}
+
+impl<C: BlockCipher> BlockMode for Pcbc<C> {
+    fn encrypt(&self, input: &[u8]) -> Vec<u8> {
+        let bs = self.cipher.block_size();
+        let mut res = self.padding.pad(input, bs);
+        let mut chain = self.iv.clone();
+        for block in res.chunks_mut(bs) {
+            let pt = block.to_vec();
+            for (b, c) in block.iter_mut().zip(&chain) {
+                *b ^= c;
+            }
+            self.cipher.encrypt_block(block);
+            for ((c, p), b) in chain.iter_mut().zip(&pt).zip(block.iter()) {
+                *c = p ^ b;
+            }
+        }
+        res
+    }
+
+    fn decrypt(&self, input: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        let bs = self.cipher.block_size();
+        if !input.len().is_multiple_of(bs) {
+            return Err(PaddingError::InvalidLength);
+        }
+        let mut res = input.to_vec();
+        let mut chain = self.iv.clone();
+        for (block, ct) in res.chunks_mut(bs).zip(input.chunks(bs)) {
+            self.cipher.decrypt_block(block);
+            for (b, c) in block.iter_mut().zip(&chain) {
+                *b ^= c;
+            }
+            for ((c, p), b) in chain.iter_mut().zip(block.iter()).zip(ct) {
+                *c = p ^ b;
+            }
+        }
+        self.padding.unpad(res, bs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{aes::soft::Aes, modes::{Cbc, ToyCipher}};
+
+    use super::*;
+
+    #[test]
+    fn pcbc_round_trip() {
+        let pcbc = Pcbc::new(Aes::new("YELLOW SUBMARINE".as_bytes()), &[1u8; 16], Padding::Pkcs7);
+        let input = "PCBC was used by Kerberos v4 and WASTE.".as_bytes();
+        let actual = pcbc.encrypt(input);
+        assert_eq!(48, actual.len());
+        assert_eq!(input, pcbc.decrypt(&actual).unwrap());
+    }
+
+    #[test]
+    fn pcbc_first_block_matches_cbc() {
+        let key = "YELLOW SUBMARINE".as_bytes();
+        let input = [b'A'; 32];
+        let pcbc = Pcbc::new(Aes::new(key), &[1u8; 16], Padding::None).encrypt(&input);
+        let cbc = Cbc::new(Aes::new(key), &[1u8; 16], Padding::None).encrypt(&input);
+        assert_eq!(cbc[..16], pcbc[..16]);
+        assert_ne!(cbc[16..], pcbc[16..]);
+    }
+
+    #[test]
+    fn pcbc_errors_propagate() {
+        let pcbc = Pcbc::new(ToyCipher { key: *b"toy key!" }, b"toy iv!!", Padding::None);
+        let input = "ABCDEFGHIJKLMNOPQRSTUVWX".as_bytes();
+        let mut ct = pcbc.encrypt(input);
+        ct[0] ^= 1;
+        let actual = pcbc.decrypt(&ct).unwrap();
+        for (a, b) in actual.chunks(8).zip(input.chunks(8)) {
+            assert_ne!(a, b);
+        }
+    }
+}
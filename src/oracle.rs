@@ -0,0 +1,278 @@
+//! The oracles the attacks talk to. Each attack is written against one of
+//! these traits rather than a concrete server, so the same attack runs
+//! against the challenge oracles, closures in tests, or an [`Instrumented`]
+//! wrapper that counts and limits what the attack asks for.
+//!
+//! Oracles take `&mut self` so they can hold an RNG or state of their own.
+
+use std::time::Duration;
+
+/// Encrypts chosen plaintexts under a key the caller can't see.
+pub trait EncryptionOracle {
+    fn encrypt(&mut self, input: &[u8]) -> Vec<u8>;
+}
+
+/// Decrypts chosen ciphertexts under a key the caller can't see. Returns
+/// None if the ciphertext is rejected.
+pub trait DecryptionOracle {
+    fn decrypt(&mut self, ciphertext: &[u8]) -> Option<Vec<u8>>;
+}
+
+/// Decrypts a CBC ciphertext under a hidden key and reveals only whether
+/// the padding was valid.
+pub trait PaddingOracle {
+    fn padding_valid(&mut self, iv: &[u8], ciphertext: &[u8]) -> bool;
+}
+
+/// Accepts or rejects a token, such as a cookie that must grant admin.
+pub trait ValidationOracle {
+    fn validate(&mut self, input: &[u8]) -> bool;
+}
+
+/// Replaces the plaintext at `offset` of a ciphertext under a hidden key
+/// and returns the new ciphertext, as a service offering random access
+/// writes to encrypted storage would.
+pub trait EditOracle {
+    fn edit(&mut self, ciphertext: &[u8], offset: usize, newtext: &[u8]) -> Vec<u8>;
+}
+
+impl<F: FnMut(&[u8]) -> Vec<u8>> EncryptionOracle for F {
+    fn encrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        self(input)
+    }
+}
+
+impl<F: FnMut(&[u8]) -> Option<Vec<u8>>> DecryptionOracle for F {
+    fn decrypt(&mut self, ciphertext: &[u8]) -> Option<Vec<u8>> {
+        self(ciphertext)
+    }
+}
+
+impl<F: FnMut(&[u8], &[u8]) -> bool> PaddingOracle for F {
+    fn padding_valid(&mut self, iv: &[u8], ciphertext: &[u8]) -> bool {
+        self(iv, ciphertext)
+    }
+}
+
+impl<F: FnMut(&[u8]) -> bool> ValidationOracle for F {
+    fn validate(&mut self, input: &[u8]) -> bool {
+        self(input)
+    }
+}
+
+impl<F: FnMut(&[u8], usize, &[u8]) -> Vec<u8>> EditOracle for F {
+    fn edit(&mut self, ciphertext: &[u8], offset: usize, newtext: &[u8]) -> Vec<u8> {
+        self(ciphertext, offset, newtext)
+    }
+}
+
+/// One query made through an [`Instrumented`] oracle. Padding oracle input
+/// is the IV followed by the ciphertext, and yes/no answers are recorded as
+/// a single byte of 1 or 0. Edit input is the offset as 8 little endian
+/// bytes followed by the new text. A rejected decryption has no output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    pub input: Vec<u8>,
+    pub output: Option<Vec<u8>>,
+}
+
+/// Wraps an oracle to count the queries made to it, optionally keeping a
+/// transcript, capping the number of queries and slowing each one down.
+/// It implements whichever oracle traits the inner oracle does, so it can
+/// be handed straight to an attack.
+pub struct Instrumented<O> {
+    inner: O,
+    queries: usize,
+    budget: Option<usize>,
+    exhausted: bool,
+    latency: Option<Duration>,
+    transcript: Option<Vec<Query>>,
+}
+
+impl<O> Instrumented<O> {
+    pub fn new(inner: O) -> Instrumented<O> {
+        Instrumented { inner, queries: 0, budget: None, exhausted: false, latency: None, transcript: None }
+    }
+
+    /// Answer at most `budget` queries. Past that every query is refused
+    /// without reaching the inner oracle: encryption returns nothing,
+    /// decryption, padding and validation queries are rejected, and edits
+    /// leave the ciphertext as it was. Check [`budget_exhausted`] to tell an
+    /// attack that ran out from one that failed.
+    ///
+    /// [`budget_exhausted`]: Instrumented::budget_exhausted
+    pub fn with_budget(mut self, budget: usize) -> Instrumented<O> {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Sleep for `latency` before answering each query, as a remote oracle
+    /// would.
+    pub fn with_latency(mut self, latency: Duration) -> Instrumented<O> {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Record every query and its answer.
+    pub fn with_transcript(mut self) -> Instrumented<O> {
+        self.transcript = Some(vec![]);
+        self
+    }
+
+    /// The number of queries answered so far.
+    pub fn queries(&self) -> usize {
+        self.queries
+    }
+
+    /// Whether a query has been refused for going over the budget.
+    pub fn budget_exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    /// The queries made so far, or an empty slice if the transcript isn't
+    /// being kept.
+    pub fn transcript(&self) -> &[Query] {
+        self.transcript.as_deref().unwrap_or(&[])
+    }
+
+    pub fn get_ref(&self) -> &O {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> O {
+        self.inner
+    }
+
+    /// Account for one query, then answer it with `f`. Returns None without
+    /// asking the inner oracle once the budget is spent.
+    fn query<T, F>(&mut self, input: impl FnOnce() -> Vec<u8>, f: F, record: impl FnOnce(&T) -> Option<Vec<u8>>) -> Option<T>
+    where
+        F: FnOnce(&mut O) -> T,
+    {
+        if self.budget.is_some_and(|budget| self.queries >= budget) {
+            self.exhausted = true;
+            return None;
+        }
+        self.queries += 1;
+        if let Some(latency) = self.latency {
+            std::thread::sleep(latency);
+        }
+        let res = f(&mut self.inner);
+        if let Some(transcript) = &mut self.transcript {
+            transcript.push(Query { input: input(), output: record(&res) });
+        }
+        Some(res)
+    }
+}
+
+impl<O: EncryptionOracle> EncryptionOracle for Instrumented<O> {
+    fn encrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        self.query(|| input.to_vec(), |o| o.encrypt(input), |ct| Some(ct.clone())).unwrap_or_default()
+    }
+}
+
+impl<O: DecryptionOracle> DecryptionOracle for Instrumented<O> {
+    fn decrypt(&mut self, ciphertext: &[u8]) -> Option<Vec<u8>> {
+        self.query(|| ciphertext.to_vec(), |o| o.decrypt(ciphertext), |pt| pt.clone()).flatten()
+    }
+}
+
+impl<O: PaddingOracle> PaddingOracle for Instrumented<O> {
+    fn padding_valid(&mut self, iv: &[u8], ciphertext: &[u8]) -> bool {
+        self.query(|| [iv, ciphertext].concat(), |o| o.padding_valid(iv, ciphertext), |&v| Some(vec![v as u8]))
+            .unwrap_or(false)
+    }
+}
+
+impl<O: ValidationOracle> ValidationOracle for Instrumented<O> {
+    fn validate(&mut self, input: &[u8]) -> bool {
+        self.query(|| input.to_vec(), |o| o.validate(input), |&v| Some(vec![v as u8])).unwrap_or(false)
+    }
+}
+
+impl<O: EditOracle> EditOracle for Instrumented<O> {
+    fn edit(&mut self, ciphertext: &[u8], offset: usize, newtext: &[u8]) -> Vec<u8> {
+        let input = || [&(offset as u64).to_le_bytes()[..], newtext].concat();
+        self.query(input, |o| o.edit(ciphertext, offset, newtext), |ct| Some(ct.clone()))
+            .unwrap_or_else(|| ciphertext.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    fn reverse(input: &[u8]) -> Vec<u8> {
+        input.iter().rev().copied().collect()
+    }
+
+    #[test]
+    fn instrumented_counts_and_records() {
+        let mut oracle = Instrumented::new(reverse).with_transcript();
+        assert_eq!(b"cba".to_vec(), oracle.encrypt(b"abc"));
+        assert_eq!(b"".to_vec(), oracle.encrypt(b""));
+        assert_eq!(2, oracle.queries());
+        let expected = [
+            Query { input: b"abc".to_vec(), output: Some(b"cba".to_vec()) },
+            Query { input: vec![], output: Some(vec![]) },
+        ];
+        assert_eq!(expected.as_slice(), oracle.transcript());
+    }
+
+    #[test]
+    fn instrumented_transcript_is_opt_in() {
+        let mut oracle = Instrumented::new(|input: &[u8]| input.len() > 2);
+        assert!(!oracle.validate(b"ab"));
+        assert!(oracle.validate(b"abc"));
+        assert_eq!(2, oracle.queries());
+        assert!(oracle.transcript().is_empty());
+    }
+
+    #[test]
+    fn instrumented_records_every_kind() {
+        let mut padding = Instrumented::new(|iv: &[u8], ct: &[u8]| iv == ct).with_transcript();
+        assert!(padding.padding_valid(&[1], &[1]));
+        assert_eq!(Query { input: vec![1, 1], output: Some(vec![1]) }, padding.transcript()[0]);
+
+        let mut decryption = Instrumented::new(|ct: &[u8]| if ct.is_empty() { None } else { Some(reverse(ct)) })
+            .with_transcript();
+        assert_eq!(None, decryption.decrypt(&[]));
+        assert_eq!(Some(vec![2, 1]), decryption.decrypt(&[1, 2]));
+        assert_eq!(None, decryption.transcript()[0].output);
+
+        let mut edit = Instrumented::new(|ct: &[u8], offset: usize, new: &[u8]| [&ct[..offset], new].concat())
+            .with_transcript();
+        assert_eq!(vec![1, 9], edit.edit(&[1, 2], 1, &[9]));
+        assert_eq!(Query { input: vec![1, 0, 0, 0, 0, 0, 0, 0, 9], output: Some(vec![1, 9]) }, edit.transcript()[0]);
+    }
+
+    #[test]
+    fn instrumented_enforces_budget() {
+        let mut oracle = Instrumented::new(reverse).with_budget(3).with_transcript();
+        for _ in 0..3 {
+            assert_eq!(b"cba".to_vec(), oracle.encrypt(b"abc"));
+        }
+        assert!(!oracle.budget_exhausted());
+        assert_eq!(Vec::<u8>::new(), oracle.encrypt(b"abc"));
+        assert!(oracle.budget_exhausted());
+        assert_eq!(3, oracle.queries());
+        assert_eq!(3, oracle.transcript().len());
+
+        let mut validation = Instrumented::new(|_: &[u8]| true).with_budget(0);
+        assert!(!validation.validate(b"admin"));
+        assert!(validation.budget_exhausted());
+        let mut edit = Instrumented::new(|_: &[u8], _: usize, new: &[u8]| new.to_vec()).with_budget(0);
+        assert_eq!(vec![1, 2], edit.edit(&[1, 2], 0, &[3]));
+    }
+
+    #[test]
+    fn instrumented_latency() {
+        let mut oracle = Instrumented::new(reverse).with_latency(Duration::from_millis(5));
+        let start = Instant::now();
+        oracle.encrypt(b"abc");
+        oracle.encrypt(b"abc");
+        assert!(start.elapsed() >= Duration::from_millis(10));
+    }
+}
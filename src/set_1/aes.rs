@@ -1,10 +1,15 @@
 use std::collections::HashSet;
 
-use openssl::symm::{Cipher, decrypt};
+use crate::{aes::Aes, modes::padding::{pkcs7_unpad, PaddingError}};
 
-pub fn decrypt_aes_128_ecb(data: &[u8], key: &[u8]) -> Vec<u8> {
-    let c = Cipher::aes_128_ecb();
-    decrypt(c, key, None, data).unwrap()
+/// Decrypts AES 128 ECB data, then checks and strips the PKCS#7 padding.
+pub fn decrypt_aes_128_ecb(data: &[u8], key: &[u8]) -> Result<Vec<u8>, PaddingError> {
+    if !data.len().is_multiple_of(16) {
+        return Err(PaddingError::InvalidLength);
+    }
+    let mut res = data.to_vec();
+    Aes::new(key).decrypt_blocks(&mut res);
+    pkcs7_unpad(&res, 16)
 }
 
 /// Indicates if the data was encrypted with aes 128 ecb. ECB is stateless
@@ -16,11 +21,11 @@ pub fn decrypt_aes_128_ecb(data: &[u8], key: &[u8]) -> Vec<u8> {
 /// to identify if this is the case.
 pub fn detect_aes_128_ecb(data: &[u8]) -> bool {
     let mut blocks: HashSet<&[u8]> = HashSet::new();
-    for i in (0..data.len()).step_by(16) {
-        if blocks.contains(&data[i..i+16]) {
+    for block in data.chunks_exact(16) {
+        if blocks.contains(block) {
             return true;
         }
-        blocks.insert(&data[i..i+16]);
+        blocks.insert(block);
     }
     false
 }
@@ -38,7 +43,7 @@ mod tests {
         let input = read_to_string("./data/set_1/ch7.txt").unwrap();
         let input = base64_to_bytes(&input.as_bytes());
         let key = "YELLOW SUBMARINE".as_bytes();
-        let actual = decrypt_aes_128_ecb(&input, key);
+        let actual = decrypt_aes_128_ecb(&input, key).unwrap();
         println!("{}", from_utf8(&actual).unwrap());
     }
 
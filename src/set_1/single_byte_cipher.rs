@@ -9,7 +9,7 @@ pub fn solve_one_byte_cipher(input: &[u8]) -> (Vec<u8>, u8) {
     let mut max_score: i32 = i32::MIN;
     let mut candidate: u8 = 0;
     let mut candidate_pt: Vec<u8> = vec![];
-    for c in 0..u8::MAX {
+    for c in 0..=u8::MAX {
         let decrypted = xor_text(&input, c);
         let score = english_score(&decrypted);
         if score > max_score {
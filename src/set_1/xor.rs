@@ -52,18 +52,7 @@ pub fn break_key_repeat_xor(input: &[u8]) -> Vec<u8> {
     for _ in 0..2 {
         let ks = ks_cands.pop().unwrap().1;
         eprintln!("Trying keysize: {}", ks);
-        let mut blocks = Vec::with_capacity(ks);
-        for i in 0..ks {
-            let mut block = vec![];
-            for j in (i..input.len()).step_by(ks) {
-                block.push(input[j]);
-            }
-            blocks.push(block)
-        }
-        let mut key: Vec<u8> = vec![];
-        for block in blocks {
-            key.push(solve_one_byte_cipher(&block).1);
-        }
+        let key = break_key_repeat_xor_keysize(input, ks);
         let text = repeating_key_xor(input, &key);
         let score = english_score(&text);
         if score > max_score {
@@ -74,6 +63,67 @@ pub fn break_key_repeat_xor(input: &[u8]) -> Vec<u8> {
     best_v
 }
 
+/// Break repeating key XOR when the keysize is already known. Every ks-th
+/// byte is XORed with the same key byte, so each of those columns is solved
+/// as a single byte XOR. Returns the key.
+pub fn break_key_repeat_xor_keysize(input: &[u8], ks: usize) -> Vec<u8> {
+    let mut key = Vec::with_capacity(ks);
+    for i in 0..ks {
+        let block: Vec<u8> = input.iter().skip(i).step_by(ks).copied().collect();
+        key.push(solve_one_byte_cipher(&block).1);
+    }
+    key
+}
+
+/// Break repeating key XOR when part of the plaintext is known. The crib is
+/// XORed against the ciphertext to give a run of keystream, and since the
+/// keystream repeats with the key, the smallest period of that run is the
+/// keysize. If `offset` is None every position in the ciphertext is tried and
+/// the candidate plaintext that scores best as english is returned.
+///
+/// The crib needs to be at least twice the length of the key so that the
+/// period can actually be checked. Returns a tuple of (plaintext, key).
+pub fn break_key_repeat_xor_crib(input: &[u8], crib: &[u8], offset: Option<usize>) -> Option<(Vec<u8>, Vec<u8>)> {
+    if crib.is_empty() || crib.len() > input.len() {
+        return None;
+    }
+    let offsets = match offset {
+        Some(o) if o.checked_add(crib.len()).is_some_and(|end| end <= input.len()) => o..o + 1,
+        Some(_) => return None,
+        None => 0..input.len() - crib.len() + 1,
+    };
+    let mut best = None;
+    let mut max_score = i32::MIN;
+    for o in offsets {
+        let keystream = fixed_xor(&input[o..o + crib.len()], crib);
+        let ks = match keystream_period(&keystream) {
+            Some(ks) => ks,
+            None => continue,
+        };
+        // The keystream starts at offset o, so rotate it back to line the
+        // key up with the start of the ciphertext.
+        let mut key = vec![0; ks];
+        for i in 0..ks {
+            key[(o + i) % ks] = keystream[i];
+        }
+        let text = repeating_key_xor(input, &key);
+        let score = english_score(&text);
+        if score > max_score {
+            max_score = score;
+            best = Some((text, key));
+        }
+    }
+    best
+}
+
+/// Find the smallest period of a run of keystream, only considering periods
+/// that repeat at least twice within it.
+fn keystream_period(keystream: &[u8]) -> Option<usize> {
+    (1..=keystream.len() / 2).find(|&ks| {
+        (ks..keystream.len()).all(|i| keystream[i] == keystream[i - ks])
+    })
+}
+
 /// Pick the keysize for the encrypted text. Text needs to be at least 40
 /// chars long. Returns a binary heap of tuples (normalized_score, keysize).
 fn pick_keysize(input: &[u8]) -> BinaryHeap<(NotNan<f64>, usize)> {
@@ -137,4 +187,47 @@ mod tests {
         let expected = read_to_string("./data/set_1/ch6-solution.txt").unwrap();
         let actual = break_key_repeat_xor(&input);
     }
+
+    #[test]
+    fn break_key_repeat_xor_keysize_happy() {
+        let input = read_to_string("./data/set_1/ch6.txt").unwrap();
+        let input = base64_decode(input.as_bytes());
+        assert_eq!("Terminator X: Bring the noise".as_bytes(), break_key_repeat_xor_keysize(&input, 29));
+    }
+
+    #[test]
+    fn break_key_repeat_xor_crib_known_offset() {
+        let input = read_to_string("./data/set_1/ch6.txt").unwrap();
+        let input = base64_decode(input.as_bytes());
+        let crib = "I'm back and I'm ringin' the bell \nA rockin' on the mike while".as_bytes();
+        let (actual, key) = break_key_repeat_xor_crib(&input, crib, Some(0)).unwrap();
+        assert_eq!("Terminator X: Bring the noise".as_bytes(), key);
+        assert_eq!(repeating_key_xor(&input, &key), actual);
+        assert!(actual.starts_with(crib));
+    }
+
+    #[test]
+    fn break_key_repeat_xor_crib_unknown_offset() {
+        let plaintext = read_to_string("./data/set_1/ch6-solution.txt").unwrap();
+        let key = "ICE".as_bytes();
+        let input = repeating_key_xor(plaintext.as_bytes(), key);
+        let crib = "Well that's my DJ Deshay".as_bytes();
+        let (actual, actual_key) = break_key_repeat_xor_crib(&input, crib, None).unwrap();
+        assert_eq!(key, actual_key);
+        assert_eq!(plaintext.as_bytes(), actual);
+    }
+
+    #[test]
+    fn break_key_repeat_xor_crib_too_short() {
+        let input = repeating_key_xor("Burning 'em, if you ain't quick".as_bytes(), "ICE".as_bytes());
+        assert_eq!(None, break_key_repeat_xor_crib(&input, "Burn".as_bytes(), Some(0)));
+    }
+
+    #[test]
+    fn break_key_repeat_xor_crib_offset_out_of_range() {
+        let input = repeating_key_xor("Burning 'em, if you ain't quick".as_bytes(), "ICE".as_bytes());
+        let crib = "Burning 'em".as_bytes();
+        assert_eq!(None, break_key_repeat_xor_crib(&input, crib, Some(input.len() - crib.len() + 1)));
+        assert_eq!(None, break_key_repeat_xor_crib(&input, crib, Some(usize::MAX)));
+    }
 }
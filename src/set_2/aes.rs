@@ -1,85 +1,114 @@
-use openssl::symm::{Cipher, Crypter, Mode};
-
-/// Encrypts data given a key and an optional IV with AES 128 CBC mode.
-pub fn encrypt_aes_128_cbc(input: &[u8], key: &[u8], iv: Option<&[u8]>) -> Vec<u8> {
-    let mut res = vec![];
-    let padded_input = pkcs7_padding(input, 16);
-
-    let mut prev_block = match iv {
-        Some(block) => block.to_owned(),
-        None => vec![b'\0'; 16]
-    };
-
-    for i in (0..padded_input.len()).step_by(16) {
-        let mut tmp = [b'\0'; 16];
-        for j in 0..16 {
-            tmp[j] = padded_input[i+j] ^ prev_block[j];
-        }
-        let ct = encrypt_aes_128_ecb(&tmp, &key);
-        res.extend_from_slice(&ct);
-        prev_block = ct;
-    }
+use crate::{aes::Aes128, modes::{Cbc, CbcCts, Cfb, Ctr, CtrLayout, BlockMode, Cts, Ofb, Padding, Pcbc, StreamMode}};
+
+pub use crate::modes::padding::{
+    ansi_x923_padding, ansi_x923_unpad, iso_10126_padding, iso_10126_padding_with_rng, iso_10126_unpad, iso_7816_padding,
+    iso_7816_unpad, pkcs7_padding, pkcs7_unpad, pkcs7_valid, zero_padding, zero_unpad, PaddingError,
+};
 
-    res
+/// Set up an AES 128 key once for a whole message. Panics if the key isn't
+/// 16 bytes.
+fn aes_128(key: &[u8]) -> Aes128 {
+    Aes128::new(key.try_into().expect("AES 128 keys are 16 bytes"))
 }
 
-pub fn decrypt_aes_128_cbc(input: &[u8], key: &[u8], iv: Option<&[u8]>) -> Vec<u8> {
-    let mut res = vec![];
+/// Encrypts data given a key and an optional IV with AES 128 CBC mode. A
+/// missing IV is all zeros.
+pub fn encrypt_aes_128_cbc(input: &[u8], key: &[u8], iv: Option<&[u8]>, padding: Padding) -> Vec<u8> {
+    let iv = iv.unwrap_or(&[0u8; 16]);
+    Cbc::new(aes_128(key), iv, padding).encrypt(input)
+}
 
-    let mut prev_block = match iv {
-        Some(block) => block.to_owned(),
-        None => vec![b'\0'; 16]
-    };
+/// Decrypts AES 128 CBC data, then checks and strips the padding.
+pub fn decrypt_aes_128_cbc(input: &[u8], key: &[u8], iv: Option<&[u8]>, padding: Padding) -> Result<Vec<u8>, PaddingError> {
+    let iv = iv.unwrap_or(&[0u8; 16]);
+    Cbc::new(aes_128(key), iv, padding).decrypt(input)
+}
 
-    for i in (0..input.len()).step_by(16) {
-        let tmp = input[i..i+16].to_vec();
-        let mut ct = decrypt_aes_128_ecb(&tmp, &key);
-        for j in 0..16 {
-            ct[j] = ct[j] ^ prev_block[j];
-        }
-        res.extend_from_slice(&ct);
-        prev_block = tmp;
-    }
+/// Encrypts data with AES 128 CBC and ciphertext stealing, so the
+/// ciphertext is as long as the input. A missing IV is all zeros. Input
+/// shorter than one block is rejected.
+pub fn encrypt_aes_128_cbc_cts(input: &[u8], key: &[u8], iv: Option<&[u8]>, variant: Cts) -> Result<Vec<u8>, PaddingError> {
+    let iv = iv.unwrap_or(&[0u8; 16]);
+    CbcCts::new(aes_128(key), iv, variant).encrypt(input)
+}
 
-    res
+/// Decrypts AES 128 CBC data with ciphertext stealing.
+pub fn decrypt_aes_128_cbc_cts(input: &[u8], key: &[u8], iv: Option<&[u8]>, variant: Cts) -> Result<Vec<u8>, PaddingError> {
+    let iv = iv.unwrap_or(&[0u8; 16]);
+    CbcCts::new(aes_128(key), iv, variant).decrypt(input)
 }
 
-pub fn encrypt_aes_128_ecb(data: &[u8], key: &[u8]) -> Vec<u8> {
-    let input = pkcs7_padding(data, 16);
-    let c = Cipher::aes_128_ecb();
-    let mut encrypter = Crypter::new(c, Mode::Encrypt, key, None).unwrap();
-    encrypter.pad(false);
-    let mut ciphertext = vec![0; data.len() + 16];
-    for i in (0..data.len()).step_by(16) {
-        encrypter.update(&data[i..i+16], &mut ciphertext[i/16..]).unwrap();
-    }
-    ciphertext.resize(data.len(), b'\0');
-    ciphertext
+/// Encrypts data with AES 128 PCBC mode. A missing IV is all zeros.
+pub fn encrypt_aes_128_pcbc(input: &[u8], key: &[u8], iv: Option<&[u8]>, padding: Padding) -> Vec<u8> {
+    let iv = iv.unwrap_or(&[0u8; 16]);
+    Pcbc::new(aes_128(key), iv, padding).encrypt(input)
 }
 
-pub fn decrypt_aes_128_ecb(data: &[u8], key: &[u8]) -> Vec<u8> {
-    let c = Cipher::aes_128_ecb();
-    let mut encrypter = Crypter::new(c, Mode::Decrypt, key, None).unwrap();
-    let mut ciphertext = vec![0; data.len() + 16];
-    encrypter.pad(false);
-    for i in (0..data.len()).step_by(16) {
-        encrypter.update(&data[i..i+16], &mut ciphertext[i/16..]).unwrap();
-    }
-    ciphertext.resize(data.len(), b'\0');
-    ciphertext
+/// Decrypts AES 128 PCBC data, then checks and strips the padding.
+pub fn decrypt_aes_128_pcbc(input: &[u8], key: &[u8], iv: Option<&[u8]>, padding: Padding) -> Result<Vec<u8>, PaddingError> {
+    let iv = iv.unwrap_or(&[0u8; 16]);
+    Pcbc::new(aes_128(key), iv, padding).decrypt(input)
 }
 
-pub fn pkcs7_padding(input: &[u8], block_length: usize) -> Vec<u8> {
-    let mut res = vec![];
+/// Encrypts data with AES 128 CFB mode, feeding back `segment_bits` at a
+/// time. A missing IV is all zeros. See [`Cfb::new`] for the segment sizes
+/// allowed.
+pub fn encrypt_aes_128_cfb(input: &[u8], key: &[u8], iv: Option<&[u8]>, segment_bits: usize) -> Vec<u8> {
+    let iv = iv.unwrap_or(&[0u8; 16]);
+    Cfb::new(aes_128(key), iv, segment_bits).encrypt(input)
+}
 
-    for i in 0..input.len() {
-        res.push(input[i]);
-    }
-    while res.len() % block_length != 0 {
-        res.push(b'\x04');
-    }
+/// Decrypts AES 128 CFB data.
+pub fn decrypt_aes_128_cfb(input: &[u8], key: &[u8], iv: Option<&[u8]>, segment_bits: usize) -> Vec<u8> {
+    let iv = iv.unwrap_or(&[0u8; 16]);
+    Cfb::new(aes_128(key), iv, segment_bits).decrypt(input)
+}
+
+/// Encrypts data with AES 128 OFB mode. A missing IV is all zeros.
+pub fn encrypt_aes_128_ofb(input: &[u8], key: &[u8], iv: Option<&[u8]>) -> Vec<u8> {
+    let iv = iv.unwrap_or(&[0u8; 16]);
+    Ofb::new(aes_128(key), iv).encrypt(input)
+}
 
-    res
+/// Decrypts AES 128 OFB data, which is the same operation as encrypting.
+pub fn decrypt_aes_128_ofb(input: &[u8], key: &[u8], iv: Option<&[u8]>) -> Vec<u8> {
+    encrypt_aes_128_ofb(input, key, iv)
+}
+
+/// Encrypts data with AES 128 CTR mode, using the cryptopals counter layout
+/// of a 64 bit little endian nonce followed by a 64 bit little endian block
+/// counter. See [`Ctr`] for random access and other counter layouts.
+pub fn encrypt_aes_128_ctr(input: &[u8], key: &[u8], nonce: u64) -> Vec<u8> {
+    Ctr::new(aes_128(key), CtrLayout::LittleEndian64 { nonce }).encrypt(input)
+}
+
+/// Decrypts AES 128 CTR data, which is the same operation as encrypting.
+pub fn decrypt_aes_128_ctr(input: &[u8], key: &[u8], nonce: u64) -> Vec<u8> {
+    encrypt_aes_128_ctr(input, key, nonce)
+}
+
+/// Replaces the plaintext at byte `offset` of an AES 128 CTR ciphertext
+/// with `newtext`, without decrypting the rest. See [`Ctr::edit`].
+pub fn edit_aes_128_ctr(ciphertext: &[u8], key: &[u8], nonce: u64, offset: usize, newtext: &[u8]) -> Vec<u8> {
+    Ctr::new(aes_128(key), CtrLayout::LittleEndian64 { nonce }).edit(ciphertext, offset, newtext)
+}
+
+/// Encrypts data with AES 128 ECB mode. With `Padding::None` the data must
+/// already be a multiple of 16 bytes.
+pub fn encrypt_aes_128_ecb(data: &[u8], key: &[u8], padding: Padding) -> Vec<u8> {
+    let mut ciphertext = padding.pad(data, 16);
+    aes_128(key).encrypt_blocks(&mut ciphertext);
+    ciphertext
+}
+
+/// Decrypts AES 128 ECB data, then checks and strips the padding.
+pub fn decrypt_aes_128_ecb(data: &[u8], key: &[u8], padding: Padding) -> Result<Vec<u8>, PaddingError> {
+    if !data.len().is_multiple_of(16) {
+        return Err(PaddingError::InvalidLength);
+    }
+    let mut plaintext = data.to_vec();
+    aes_128(key).decrypt_blocks(&mut plaintext);
+    padding.unpad(plaintext, 16)
 }
 
 #[cfg(test)]
@@ -90,22 +119,129 @@ mod tests {
 
     use super::*;
 
-    #[test]
-    fn pkcs7_padding_happy() {
-        let input = "YELLOW SUBMARINE".as_bytes();
-        let expected = "YELLOW SUBMARINE\x04\x04\x04\x04".as_bytes();
-        let actual = pkcs7_padding(&input, 20);
-        assert_eq!(expected, actual);
-    }
-
     #[test]
     fn aes_cbc_happy() {
         let input = "This is a 48-byte message (exactly 3 AES blocks)".as_bytes();
         let key = hex_decode("6c3ea0477630ce21a2ce334aa746c2cd".as_bytes());
         let iv = hex_decode("c782dc4c098c66cbd9cd27d825682c81".as_bytes());
         let expected = "d0a02b3836451753d493665d33f0e8862dea54cdb293abc7506939276772f8d5021c19216bad525c8579695d83ba2684".as_bytes();
-        let actual = encrypt_aes_128_cbc(&input, &key, Some(&iv));
+        let actual = encrypt_aes_128_cbc(&input, &key, Some(&iv), Padding::Pkcs7);
+        // The message is block aligned, so a whole block of padding follows.
+        assert_eq!(64, actual.len());
+        assert_eq!(expected, &hex_encode(&actual)[..96]);
+        assert_eq!(input, decrypt_aes_128_cbc(&actual, &key, Some(&iv), Padding::Pkcs7).unwrap());
+    }
+
+    const NIST_KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const NIST_PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e5130c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+    // NIST SP 800-38A F.1.1 / F.1.2
+    #[test]
+    fn aes_ecb_nist_vector() {
+        let key = hex_decode(NIST_KEY.as_bytes());
+        let input = hex_decode(NIST_PLAINTEXT.as_bytes());
+        let expected = "3ad77bb40d7a3660a89ecaf32466ef97f5d3d58503b9699de785895a96fdbaaf43b1cd7f598ece23881b00e3ed0306887b0c785e27e8ad3f8223207104725dd4".as_bytes();
+        let actual = encrypt_aes_128_ecb(&input, &key, Padding::None);
         assert_eq!(expected, hex_encode(&actual));
+        assert_eq!(input, decrypt_aes_128_ecb(&actual, &key, Padding::None).unwrap());
+    }
+
+    // NIST SP 800-38A F.2.1 / F.2.2
+    #[test]
+    fn aes_cbc_nist_vector() {
+        let key = hex_decode(NIST_KEY.as_bytes());
+        let iv = hex_decode("000102030405060708090a0b0c0d0e0f".as_bytes());
+        let input = hex_decode(NIST_PLAINTEXT.as_bytes());
+        let expected = "7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b273bed6b8e3c1743b7116e69e222295163ff1caa1681fac09120eca307586e1a7".as_bytes();
+        let actual = encrypt_aes_128_cbc(&input, &key, Some(&iv), Padding::Pkcs7);
+        assert_eq!(expected, &hex_encode(&actual)[..128]);
+        assert_eq!(input, decrypt_aes_128_cbc(&actual, &key, Some(&iv), Padding::Pkcs7).unwrap());
+    }
+
+    #[test]
+    fn aes_cbc_cts_round_trip() {
+        let key = "YELLOW SUBMARINE".as_bytes();
+        let iv = [7u8; 16];
+        for variant in [Cts::Cs1, Cts::Cs2, Cts::Cs3] {
+            for n in 16..50 {
+                let input = vec![b'A'; n];
+                let ct = encrypt_aes_128_cbc_cts(&input, key, Some(&iv), variant).unwrap();
+                assert_eq!(n, ct.len());
+                assert_eq!(input, decrypt_aes_128_cbc_cts(&ct, key, Some(&iv), variant).unwrap());
+            }
+        }
+        // RFC 3962 appendix B, the 17 byte message.
+        let key = hex_decode("636869636b656e207465726979616b69".as_bytes());
+        let ct = encrypt_aes_128_cbc_cts("I would like the ".as_bytes(), &key, None, Cts::Cs3).unwrap();
+        assert_eq!("c6353568f2bf8cb4d8a580362da7ff7f97".as_bytes(), hex_encode(&ct));
+        assert_eq!(Err(PaddingError::InvalidLength), encrypt_aes_128_cbc_cts(&[0; 15], &key, None, Cts::Cs1));
+        assert_eq!(Err(PaddingError::InvalidLength), decrypt_aes_128_cbc_cts(&[0; 15], &key, None, Cts::Cs1));
+    }
+
+    #[test]
+    fn aes_ecb_padded_round_trip() {
+        let key = "YELLOW SUBMARINE".as_bytes();
+        let input = "An ECB message spanning a few blocks, not block aligned.".as_bytes();
+        let actual = encrypt_aes_128_ecb(input, key, Padding::Pkcs7);
+        assert_eq!(64, actual.len());
+        assert_eq!(input, decrypt_aes_128_ecb(&actual, key, Padding::Pkcs7).unwrap());
+    }
+
+    #[test]
+    fn aes_decrypt_rejects_unaligned() {
+        let key = "YELLOW SUBMARINE".as_bytes();
+        assert_eq!(Err(PaddingError::InvalidLength), decrypt_aes_128_ecb(&[0u8; 15], key, Padding::Pkcs7));
+        assert_eq!(Err(PaddingError::InvalidLength), decrypt_aes_128_cbc(&[0u8; 17], key, None, Padding::None));
+    }
+
+    #[test]
+    fn aes_ecb_repeats_blocks() {
+        let key = "YELLOW SUBMARINE".as_bytes();
+        let actual = encrypt_aes_128_ecb(&[b'A'; 48], key, Padding::None);
+        assert_eq!(actual[..16], actual[16..32]);
+        assert_eq!(actual[16..32], actual[32..]);
+    }
+
+    #[test]
+    fn aes_ctr_round_trip() {
+        let key = "YELLOW SUBMARINE".as_bytes();
+        let input = "CTR needs no padding, so the length is unchanged".as_bytes();
+        let actual = encrypt_aes_128_ctr(input, key, 7);
+        assert_eq!(input.len(), actual.len());
+        assert_ne!(actual, encrypt_aes_128_ctr(input, key, 8));
+        assert_eq!(input, decrypt_aes_128_ctr(&actual, key, 7));
+    }
+
+    #[test]
+    fn aes_feedback_modes_round_trip() {
+        let key = "YELLOW SUBMARINE".as_bytes();
+        let iv = [7u8; 16];
+        let input = "Every mode gets a helper, so the oracles can use them all".as_bytes();
+
+        let pcbc = encrypt_aes_128_pcbc(input, key, Some(&iv), Padding::Pkcs7);
+        assert_eq!(64, pcbc.len());
+        assert_ne!(pcbc, encrypt_aes_128_cbc(input, key, Some(&iv), Padding::Pkcs7));
+        assert_eq!(input, decrypt_aes_128_pcbc(&pcbc, key, Some(&iv), Padding::Pkcs7).unwrap());
+
+        for segment_bits in [8, 128] {
+            let cfb = encrypt_aes_128_cfb(input, key, Some(&iv), segment_bits);
+            assert_eq!(input.len(), cfb.len());
+            assert_eq!(input, decrypt_aes_128_cfb(&cfb, key, Some(&iv), segment_bits));
+        }
+
+        let ofb = encrypt_aes_128_ofb(input, key, None);
+        assert_eq!(input.len(), ofb.len());
+        assert_eq!(input, decrypt_aes_128_ofb(&ofb, key, None));
+    }
+
+    #[test]
+    fn aes_ctr_edit() {
+        let key = "YELLOW SUBMARINE".as_bytes();
+        let ct = encrypt_aes_128_ctr("Edit one word of a CTR message".as_bytes(), key, 25);
+        let edited = edit_aes_128_ctr(&ct, key, 25, 5, "two".as_bytes());
+        assert_eq!(ct[..5], edited[..5]);
+        assert_eq!(ct[8..], edited[8..]);
+        assert_eq!("Edit two word of a CTR message".as_bytes(), decrypt_aes_128_ctr(&edited, key, 25));
     }
 
     #[test]
@@ -113,7 +249,7 @@ mod tests {
         let key = "YELLOW SUBMARINE".as_bytes();
         let input = read_to_string("./data/set_2/ch10.txt").unwrap();
         let input = base64_decode(input.as_bytes());
-        let result = decrypt_aes_128_cbc(&input, key, None);
+        let result = decrypt_aes_128_cbc(&input, key, None, Padding::Pkcs7).unwrap();
         println!("{}", from_utf8(&result).unwrap());
     }
 }
@@ -0,0 +1,148 @@
+//! CBC bitflipping. Flipping a bit in one ciphertext block scrambles that
+//! block's plaintext but flips the same bit in the next block's plaintext,
+//! so an attacker can write whatever they like into a block they know the
+//! plaintext of, at the cost of the block before it.
+
+use rand::Rng;
+
+use crate::{modes::Padding, oracle::{EncryptionOracle, ValidationOracle}};
+
+use super::{aes::{decrypt_aes_128_cbc, encrypt_aes_128_cbc}, oracle::random_key};
+
+pub const COMMENT_PREFIX: &[u8] = b"comment1=cooking%20MCs;userdata=";
+pub const COMMENT_SUFFIX: &[u8] = b";comment2=%20like%20a%20pound%20of%20bacon";
+
+/// Wraps user data in a comment string and encrypts it with AES 128 CBC
+/// under a fixed random key and IV.
+pub struct CommentOracle {
+    key: Vec<u8>,
+    iv: [u8; 16],
+}
+
+impl CommentOracle {
+    pub fn new<R: Rng>(rng: &mut R) -> CommentOracle {
+        let key = random_key(rng);
+        let mut iv = [0u8; 16];
+        rng.fill_bytes(&mut iv);
+        CommentOracle { key, iv }
+    }
+
+    /// Decrypt and look for `;admin=true;`. Scrambled blocks make the
+    /// plaintext arbitrary bytes, so it is searched as bytes rather than
+    /// parsed.
+    pub fn is_admin(&self, ciphertext: &[u8]) -> bool {
+        match decrypt_aes_128_cbc(ciphertext, &self.key, Some(&self.iv), Padding::Pkcs7) {
+            Ok(plaintext) => plaintext.windows(ADMIN.len()).any(|w| w == ADMIN),
+            Err(_) => false,
+        }
+    }
+}
+
+impl EncryptionOracle for CommentOracle {
+    /// Quote `;` and `=` in `userdata` so it can't add fields, then encrypt
+    /// it between [`COMMENT_PREFIX`] and [`COMMENT_SUFFIX`].
+    fn encrypt(&mut self, userdata: &[u8]) -> Vec<u8> {
+        let mut data = COMMENT_PREFIX.to_vec();
+        data.extend_from_slice(&quote(userdata));
+        data.extend_from_slice(COMMENT_SUFFIX);
+        encrypt_aes_128_cbc(&data, &self.key, Some(&self.iv), Padding::Pkcs7)
+    }
+}
+
+impl ValidationOracle for CommentOracle {
+    fn validate(&mut self, ciphertext: &[u8]) -> bool {
+        self.is_admin(ciphertext)
+    }
+}
+
+const ADMIN: &[u8] = b";admin=true;";
+
+/// Percent escape `;` and `=`.
+fn quote(input: &[u8]) -> Vec<u8> {
+    let mut res = Vec::with_capacity(input.len());
+    for &b in input {
+        match b {
+            b';' => res.extend_from_slice(b"%3B"),
+            b'=' => res.extend_from_slice(b"%3D"),
+            b => res.push(b),
+        }
+    }
+    res
+}
+
+/// Build a ciphertext that decrypts to something containing `;admin=true;`
+/// using only the encryption side of an oracle that behaves like
+/// [`CommentOracle`].
+///
+/// The user data is a sacrificial block followed by `;admin=true;` with the
+/// metacharacters swapped for `A`s, which pass through the quoting. XORing
+/// the difference into the sacrificial block's ciphertext turns the `A`s
+/// back into `;` and `=` in the block after it. Returns None if the oracle
+/// gives back too little ciphertext to flip, as a refused query does.
+pub fn make_admin_cbc<O: EncryptionOracle>(oracle: &mut O) -> Option<Vec<u8>> {
+    // Pad the prefix out to a block boundary, then the sacrificial block.
+    let filler = (16 - COMMENT_PREFIX.len() % 16) % 16 + 16;
+    let target: Vec<u8> = ADMIN.iter().map(|&b| if b == b';' || b == b'=' { b'A' } else { b }).collect();
+    let mut userdata = vec![b'A'; filler];
+    userdata.extend_from_slice(&target);
+
+    let mut ct = oracle.encrypt(&userdata);
+    let flip = COMMENT_PREFIX.len() + filler - 16;
+    let sacrificial = ct.get_mut(flip..flip + ADMIN.len())?;
+    for (c, (have, want)) in sacrificial.iter_mut().zip(target.iter().zip(ADMIN)) {
+        *c ^= have ^ want;
+    }
+    Some(ct)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use crate::oracle::Instrumented;
+
+    use super::*;
+
+    #[test]
+    fn quote_happy() {
+        assert_eq!(b"a%3Bb%3Dc".as_slice(), quote(b"a;b=c"));
+    }
+
+    #[test]
+    fn injection_is_quoted() {
+        let mut oracle = CommentOracle::new(&mut StdRng::seed_from_u64(16));
+        for userdata in [b";admin=true;".as_slice(), b"x;admin=true;comment3="] {
+            let ct = oracle.encrypt(userdata);
+            assert!(!oracle.is_admin(&ct));
+        }
+    }
+
+    // S2C16
+    #[test]
+    fn make_admin_cbc_happy() {
+        let mut rng = StdRng::seed_from_u64(1616);
+        for _ in 0..20 {
+            let mut oracle = Instrumented::new(CommentOracle::new(&mut rng));
+            let ct = make_admin_cbc(&mut oracle).unwrap();
+            assert!(oracle.validate(&ct));
+            assert_eq!(2, oracle.queries());
+        }
+    }
+
+    #[test]
+    fn make_admin_cbc_short_ciphertext() {
+        let mut oracle = Instrumented::new(CommentOracle::new(&mut StdRng::seed_from_u64(16))).with_budget(0);
+        assert_eq!(None, make_admin_cbc(&mut oracle));
+        assert!(oracle.budget_exhausted());
+        assert_eq!(None, make_admin_cbc(&mut |_: &[u8]| vec![0u8; 40]));
+    }
+
+    #[test]
+    fn is_admin_rejects_bad_lengths() {
+        let mut oracle = CommentOracle::new(&mut StdRng::seed_from_u64(16));
+        for ct in [&[][..], &[0u8; 15], &[0u8; 33]] {
+            assert!(!oracle.is_admin(ct));
+            assert!(!oracle.validate(ct));
+        }
+    }
+}
@@ -0,0 +1,329 @@
+//! Byte-at-a-time ECB decryption. An oracle that appends a secret to
+//! attacker controlled input and encrypts the lot under ECB leaks the secret
+//! one byte at a time: line the unknown byte up as the last byte of a block
+//! and compare that block against every possible last byte.
+
+use rand::Rng;
+
+use crate::{byte_util::base64_decode, modes::Padding, oracle::EncryptionOracle, set_1::aes::detect_aes_128_ecb};
+
+use super::{aes::encrypt_aes_128_ecb, oracle::random_key};
+
+const CHALLENGE_12: &str = include_str!("../../data/set_2/ch12.txt");
+
+/// The secret the challenge 12 and 14 oracles append.
+pub fn challenge_12_secret() -> Vec<u8> {
+    base64_decode(CHALLENGE_12.trim().as_bytes())
+}
+
+/// Encrypts `input || secret` with AES 128 ECB under a fixed random key.
+pub struct SuffixOracle {
+    key: Vec<u8>,
+    secret: Vec<u8>,
+}
+
+impl SuffixOracle {
+    pub fn new<R: Rng>(secret: &[u8], rng: &mut R) -> SuffixOracle {
+        SuffixOracle { key: random_key(rng), secret: secret.to_vec() }
+    }
+}
+
+impl EncryptionOracle for SuffixOracle {
+    fn encrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        let mut data = input.to_vec();
+        data.extend_from_slice(&self.secret);
+        encrypt_aes_128_ecb(&data, &self.key, Padding::Pkcs7)
+    }
+}
+
+/// Encrypts `prefix || input || secret` with AES 128 ECB under a fixed
+/// random key, where the prefix is random bytes of a random length.
+pub struct PrefixOracle<R> {
+    key: Vec<u8>,
+    secret: Vec<u8>,
+    prefix: Vec<u8>,
+    max_prefix: usize,
+    changing: bool,
+    rng: R,
+}
+
+impl<R: Rng> PrefixOracle<R> {
+    /// The prefix is between 0 and `max_prefix` bytes, picked once.
+    pub fn new(secret: &[u8], max_prefix: usize, rng: R) -> PrefixOracle<R> {
+        PrefixOracle::build(secret, max_prefix, false, rng)
+    }
+
+    /// A new prefix of between 0 and `max_prefix` bytes is picked for every
+    /// call.
+    pub fn changing(secret: &[u8], max_prefix: usize, rng: R) -> PrefixOracle<R> {
+        PrefixOracle::build(secret, max_prefix, true, rng)
+    }
+
+    fn build(secret: &[u8], max_prefix: usize, changing: bool, mut rng: R) -> PrefixOracle<R> {
+        let key = random_key(&mut rng);
+        let prefix = random_prefix(&mut rng, max_prefix);
+        PrefixOracle { key, secret: secret.to_vec(), prefix, max_prefix, changing, rng }
+    }
+}
+
+fn random_prefix<R: Rng>(rng: &mut R, max_len: usize) -> Vec<u8> {
+    let len = rng.gen_range(0..=max_len);
+    (0..len).map(|_| rng.gen()).collect()
+}
+
+impl<R: Rng> EncryptionOracle for PrefixOracle<R> {
+    fn encrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        if self.changing {
+            self.prefix = random_prefix(&mut self.rng, self.max_prefix);
+        }
+        let data = [&self.prefix, input, &self.secret].concat();
+        encrypt_aes_128_ecb(&data, &self.key, Padding::Pkcs7)
+    }
+}
+
+/// Recover the secret an ECB oracle appends to its input.
+///
+/// The block size and secret length come from growing the input until the
+/// ciphertext gains a block, then ECB is confirmed by looking for repeated
+/// blocks. Each secret byte then costs one query, see [`recover_suffix`].
+/// Returns None if the oracle isn't ECB. Wrap the oracle in an
+/// [`Instrumented`] to count the queries.
+///
+/// [`Instrumented`]: crate::oracle::Instrumented
+pub fn byte_at_a_time<O: EncryptionOracle>(oracle: &mut O) -> Option<Vec<u8>> {
+    let (block_size, secret_len) = find_block_size(oracle)?;
+    if !detect_aes_128_ecb(&oracle.encrypt(&vec![b'A'; 2 * block_size])) {
+        return None;
+    }
+    recover_suffix(block_size, secret_len, |input| Some(oracle.encrypt(input)))
+}
+
+/// Recover the secret an ECB oracle appends to its input when it also
+/// prepends a prefix of unknown length, which may change from call to call.
+///
+/// Every query starts with a marker of two identical blocks of 'M' followed
+/// by two identical blocks of 'N'. Like [`detect_aes_128_ecb`], this relies
+/// on equal plaintext blocks giving equal ciphertext blocks: the two pairs
+/// only both show up when the marker starts on a block boundary, and then
+/// everything after them is the encryption of `input || secret` as if there
+/// were no prefix. Filler in front of the marker is varied until it lines
+/// up, and the filler that worked is tried first next time, so a fixed
+/// prefix costs a few extra queries once and a changing prefix costs about
+/// a block size's worth of queries per aligned query.
+///
+/// Returns None if the oracle isn't ECB. Wrap the oracle in an
+/// [`Instrumented`] to count the queries.
+///
+/// [`Instrumented`]: crate::oracle::Instrumented
+pub fn byte_at_a_time_prefix<O: EncryptionOracle>(oracle: &mut O) -> Option<Vec<u8>> {
+    let block_size = find_block_size_gcd(oracle)?;
+    if !detect_aes_128_ecb(&oracle.encrypt(&vec![b'A'; 3 * block_size])) {
+        return None;
+    }
+    let mut aligner = Aligner { oracle, block_size, filler: 0 };
+
+    // Same as find_block_size, but on the aligned ciphertext so the prefix
+    // is out of the way.
+    let base = aligner.query(&[])?.len();
+    let mut secret_len = None;
+    for k in 1..=block_size {
+        if aligner.query(&vec![b'A'; k])?.len() > base {
+            secret_len = Some(base - k);
+            break;
+        }
+    }
+    recover_suffix(block_size, secret_len?, |input| aligner.query(input))
+}
+
+/// Gets the encryption of `input || secret` from behind an unknown prefix,
+/// by retrying with different filler until an aligned marker shows up.
+struct Aligner<'a, O> {
+    oracle: &'a mut O,
+    block_size: usize,
+    filler: usize,
+}
+
+impl<O: EncryptionOracle> Aligner<'_, O> {
+    /// How many times to try before deciding the marker will never line up.
+    const ATTEMPTS: usize = 64;
+
+    fn query(&mut self, input: &[u8]) -> Option<Vec<u8>> {
+        let bs = self.block_size;
+        for _ in 0..Self::ATTEMPTS * bs {
+            let mut data = vec![b'F'; self.filler];
+            data.extend(std::iter::repeat_n(b'M', 2 * bs));
+            data.extend(std::iter::repeat_n(b'N', 2 * bs));
+            data.extend_from_slice(input);
+
+            let ct = self.oracle.encrypt(&data);
+            let blocks: Vec<&[u8]> = ct.chunks(bs).collect();
+            let marker = blocks.windows(4).position(|w| w[0] == w[1] && w[2] == w[3] && w[1] != w[2]);
+            if let Some(i) = marker {
+                return Some(ct[(i + 4) * bs..].to_vec());
+            }
+            self.filler = (self.filler + 1) % bs;
+        }
+        None
+    }
+}
+
+/// Recover `secret_len` bytes of secret, given a way to get the encryption
+/// of `input || secret` starting on a block boundary.
+///
+/// Each query pads with 'A's so the next unknown byte is the last in its
+/// block, and puts all 256 candidates for that block in front. ECB encrypts
+/// every block independently, so the candidate that matches the target
+/// block gives the byte.
+fn recover_suffix<Q>(block_size: usize, secret_len: usize, mut query: Q) -> Option<Vec<u8>>
+where
+    Q: FnMut(&[u8]) -> Option<Vec<u8>>,
+{
+    let dictionary_len = 256 * block_size;
+    // `A * (block_size - 1) || secret`, the plaintext as far as is known.
+    let mut known = vec![b'A'; block_size - 1];
+    for i in 0..secret_len {
+        let window = &known[known.len() - (block_size - 1)..];
+        let mut input = Vec::with_capacity(dictionary_len + block_size);
+        for c in 0..=255u8 {
+            input.extend_from_slice(window);
+            input.push(c);
+        }
+        let pad_len = block_size - 1 - i % block_size;
+        input.extend(std::iter::repeat_n(b'A', pad_len));
+
+        let ct = query(&input)?;
+        let start = dictionary_len + (pad_len + i) / block_size * block_size;
+        let target = ct.get(start..start + block_size)?;
+        let c = ct[..dictionary_len].chunks(block_size).position(|block| block == target)?;
+        known.push(c as u8);
+    }
+    Some(known.split_off(block_size - 1))
+}
+
+/// Feed the oracle longer and longer input until the ciphertext grows. The
+/// jump is the block size, and the secret plus the input at that point
+/// exactly filled the previous length. Returns (block size, secret length).
+fn find_block_size<O: EncryptionOracle>(oracle: &mut O) -> Option<(usize, usize)> {
+    let base = oracle.encrypt(&[]).len();
+    for k in 1..=256 {
+        let len = oracle.encrypt(&vec![b'A'; k]).len();
+        if len > base {
+            return Some((len - base, base - k));
+        }
+    }
+    None
+}
+
+/// Find the block size when a changing prefix makes the ciphertext length
+/// jump around. Every length is a multiple of the block size, so take the
+/// greatest common divisor of the differences as the input grows.
+fn find_block_size_gcd<O: EncryptionOracle>(oracle: &mut O) -> Option<usize> {
+    let base = oracle.encrypt(&[]).len();
+    let mut block_size = 0;
+    for k in 1..=64 {
+        let len = oracle.encrypt(&vec![b'A'; k]).len();
+        block_size = gcd(block_size, len.abs_diff(base));
+    }
+    if block_size == 0 {
+        None
+    } else {
+        Some(block_size)
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use crate::oracle::Instrumented;
+
+    use super::{super::aes::encrypt_aes_128_cbc, *};
+
+    // S2C12
+    #[test]
+    fn byte_at_a_time_challenge_12() {
+        let secret = challenge_12_secret();
+        assert!(secret.starts_with("Rollin' in my 5.0\n".as_bytes()));
+        let mut oracle = Instrumented::new(SuffixOracle::new(&secret, &mut StdRng::seed_from_u64(1212)));
+        assert_eq!(secret, byte_at_a_time(&mut oracle).unwrap());
+        let queries = oracle.queries();
+        // One query per byte, plus up to a block's worth to find the block
+        // size and one to check for ECB.
+        assert!(queries <= secret.len() + 16 + 2, "{} queries", queries);
+    }
+
+    #[test]
+    fn byte_at_a_time_any_length() {
+        let mut rng = StdRng::seed_from_u64(12);
+        for len in [0, 1, 15, 16, 17, 47] {
+            let secret: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let mut oracle = SuffixOracle::new(&secret, &mut rng);
+            assert_eq!(secret, byte_at_a_time(&mut oracle).unwrap(), "length {}", len);
+        }
+    }
+
+    #[test]
+    fn byte_at_a_time_rejects_cbc() {
+        let secret = challenge_12_secret();
+        let key = random_key(&mut StdRng::seed_from_u64(99));
+        let mut oracle = |input: &[u8]| {
+            let data = [input, &secret].concat();
+            encrypt_aes_128_cbc(&data, &key, None, Padding::Pkcs7)
+        };
+        assert_eq!(None, byte_at_a_time(&mut oracle));
+    }
+
+    #[test]
+    fn byte_at_a_time_prefix_lengths() {
+        let secret = challenge_12_secret();
+        let mut rng = StdRng::seed_from_u64(14);
+        // Up to several blocks, either side of the block boundaries.
+        for len in [0, 1, 15, 16, 17, 31, 32, 33, 47, 100] {
+            let prefix: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let key = random_key(&mut rng);
+            let mut oracle = Instrumented::new(|input: &[u8]| {
+                let data = [&prefix, input, &secret].concat();
+                encrypt_aes_128_ecb(&data, &key, Padding::Pkcs7)
+            });
+            assert_eq!(secret, byte_at_a_time_prefix(&mut oracle).unwrap(), "prefix length {}", len);
+            let queries = oracle.queries();
+            // Lining the marker up costs at most a block's worth of queries
+            // once, after which every query lines up first time.
+            assert!(queries <= 64 + 1 + 16 + 16 + 1 + secret.len(), "{} queries", queries);
+        }
+    }
+
+    // S2C14
+    #[test]
+    fn byte_at_a_time_prefix_challenge_14() {
+        let secret = challenge_12_secret();
+        let mut oracle = PrefixOracle::new(&secret, 64, StdRng::seed_from_u64(1414));
+        assert_eq!(secret, byte_at_a_time_prefix(&mut oracle).unwrap());
+    }
+
+    #[test]
+    fn byte_at_a_time_prefix_changing() {
+        let secret = challenge_12_secret();
+        let mut oracle = Instrumented::new(PrefixOracle::changing(&secret, 80, StdRng::seed_from_u64(114)));
+        assert_eq!(secret, byte_at_a_time_prefix(&mut oracle).unwrap());
+        let queries = oracle.queries();
+        // Each aligned query takes about a block size's worth of tries, so
+        // twice that per byte is plenty.
+        assert!(queries < 2 * 16 * secret.len(), "{} queries", queries);
+    }
+
+    #[test]
+    fn byte_at_a_time_prefix_rejects_cbc() {
+        let secret = challenge_12_secret();
+        let key = random_key(&mut StdRng::seed_from_u64(99));
+        let mut oracle = |input: &[u8]| {
+            let data = [[7u8; 5].as_slice(), input, &secret].concat();
+            encrypt_aes_128_cbc(&data, &key, None, Padding::Pkcs7)
+        };
+        assert_eq!(None, byte_at_a_time_prefix(&mut oracle));
+    }
+}
@@ -1,2 +1,5 @@
 pub mod aes;
+pub mod bitflip;
+pub mod byte_at_a_time;
 pub mod oracle;
+pub mod profile;
@@ -1,38 +1,118 @@
-use rand::{rngs::OsRng, RngCore, Rng};
+use rand::Rng;
 
-use super::aes::{encrypt_aes_128_cbc, encrypt_aes_128_ecb, pkcs7_padding};
+use crate::{modes::Padding, oracle::EncryptionOracle, set_1::aes::detect_aes_128_ecb};
+
+use super::aes::{encrypt_aes_128_cbc, encrypt_aes_128_cfb, encrypt_aes_128_ecb, encrypt_aes_128_ofb, encrypt_aes_128_pcbc};
+
+/// The block cipher modes the encryption oracle can pick between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AesMode {
+    Ecb,
+    Cbc,
+    Pcbc,
+    /// CFB with a whole block fed back at a time.
+    Cfb,
+    Ofb,
+}
+
+/// The modes challenge 11's oracle picks between.
+pub const ECB_OR_CBC: [AesMode; 2] = [AesMode::Ecb, AesMode::Cbc];
 
 /// This function takes an input text, randomly prepends and postpends 5-10
-/// random bytes each, before encrypting with either AES 128 ECB or CBC.
-pub fn encryption_oracle(input: &[u8]) -> Vec<u8> {
-    let key = random_key();
-    let prepend = rand::thread_rng().gen_range(5..=10);
-    let postpend = rand::thread_rng().gen_range(5..=10);
+/// random bytes each, before encrypting with either AES 128 ECB or CBC under
+/// a random key. All the randomness comes from `rng`, so a seeded RNG gives
+/// the same result every time. Returns the ciphertext and the mode used, so
+/// guesses can be checked.
+pub fn encryption_oracle<R: Rng>(input: &[u8], rng: &mut R) -> (Vec<u8>, AesMode) {
+    encryption_oracle_with_modes(input, &ECB_OR_CBC, rng)
+}
+
+/// [`encryption_oracle`], picking uniformly from `modes` instead of just ECB
+/// and CBC, so the same experiments can be run against every mode. Panics
+/// if `modes` is empty.
+pub fn encryption_oracle_with_modes<R: Rng>(input: &[u8], modes: &[AesMode], rng: &mut R) -> (Vec<u8>, AesMode) {
+    assert!(!modes.is_empty(), "the oracle needs at least one mode to pick from");
+    let key = random_key(rng);
+    let prepend = rng.gen_range(5..=10);
+    let postpend = rng.gen_range(5..=10);
 
     let mut tmp_input = vec![];
     for _ in 0..prepend {
-        tmp_input.push(rand::thread_rng().gen_range(0..=255) as u8);
+        tmp_input.push(rng.gen::<u8>());
     }
     tmp_input.extend_from_slice(input);
     for _ in 0..postpend {
-        tmp_input.push(rand::thread_rng().gen_range(0..=255) as u8);
+        tmp_input.push(rng.gen::<u8>());
     }
 
-    let input = pkcs7_padding(input, 16);
+    let mode = modes[rng.gen_range(0..modes.len())];
+    let mut iv = [0u8; 16];
+    if mode != AesMode::Ecb {
+        rng.fill_bytes(&mut iv);
+    }
+    let ct = match mode {
+        AesMode::Ecb => encrypt_aes_128_ecb(&tmp_input, &key, Padding::Pkcs7),
+        AesMode::Cbc => encrypt_aes_128_cbc(&tmp_input, &key, Some(&iv), Padding::Pkcs7),
+        AesMode::Pcbc => encrypt_aes_128_pcbc(&tmp_input, &key, Some(&iv), Padding::Pkcs7),
+        AesMode::Cfb => encrypt_aes_128_cfb(&tmp_input, &key, Some(&iv), 128),
+        AesMode::Ofb => encrypt_aes_128_ofb(&tmp_input, &key, Some(&iv)),
+    };
+    (ct, mode)
+}
 
-    if OsRng.next_u64() % 2 == 0 {
-        let mut iv = [0u8; 16];
-        OsRng.fill_bytes(&mut iv);
-        encrypt_aes_128_cbc(&input, &key, Some(&iv))
-    } else {
+/// [`encryption_oracle`] as an [`EncryptionOracle`], owning its RNG. The
+/// mode picked for the last query is kept so guesses can be checked.
+pub struct ModeOracle<R> {
+    rng: R,
+    modes: Vec<AesMode>,
+    last_mode: Option<AesMode>,
+}
+
+impl<R: Rng> ModeOracle<R> {
+    /// Pick between ECB and CBC, as in challenge 11.
+    pub fn new(rng: R) -> ModeOracle<R> {
+        ModeOracle::with_modes(rng, &ECB_OR_CBC)
+    }
+
+    /// Pick between the given modes. Panics if `modes` is empty.
+    pub fn with_modes(rng: R, modes: &[AesMode]) -> ModeOracle<R> {
+        assert!(!modes.is_empty(), "the oracle needs at least one mode to pick from");
+        ModeOracle { rng, modes: modes.to_vec(), last_mode: None }
+    }
+
+    /// The mode used for the last query, if there has been one.
+    pub fn last_mode(&self) -> Option<AesMode> {
+        self.last_mode
+    }
+}
+
+impl<R: Rng> EncryptionOracle for ModeOracle<R> {
+    fn encrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        let (ct, mode) = encryption_oracle_with_modes(input, &self.modes, &mut self.rng);
+        self.last_mode = Some(mode);
+        ct
+    }
+}
+
+/// The input to give the oracle so its mode can be detected. However many
+/// random bytes are prepended, 43 identical bytes always fill two whole
+/// aligned blocks, which ECB will encrypt to the same ciphertext.
+pub const DETECTION_INPUT: [u8; 43] = [b'A'; 43];
 
-        encrypt_aes_128_ecb(&input, &key)
+/// Guess the mode a ciphertext of [`DETECTION_INPUT`] was encrypted with, by
+/// looking for repeated blocks. Only ECB repeats them, so any other mode is
+/// reported as CBC.
+pub fn detect_mode(ciphertext: &[u8]) -> AesMode {
+    if detect_aes_128_ecb(ciphertext) {
+        AesMode::Ecb
+    } else {
+        AesMode::Cbc
     }
 }
 
-fn random_key() -> Vec<u8> {
+pub(crate) fn random_key<R: Rng>(rng: &mut R) -> Vec<u8> {
     let mut key = [0u8; 16];
-    OsRng.fill_bytes(&mut key);
+    rng.fill_bytes(&mut key);
     key.to_vec()
 }
 
@@ -40,19 +120,76 @@ fn random_key() -> Vec<u8> {
 mod tests {
     use std::fs::read_to_string;
 
-    use crate::set_1::aes::detect_aes_128_ecb;
+    use rand::{SeedableRng, rngs::StdRng};
 
-    use super::encryption_oracle;
+    use super::*;
 
+    // S2C11
+    #[test]
+    fn detect_mode_always_right() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut seen_ecb = 0;
+        for _ in 0..1000 {
+            let (output, mode) = encryption_oracle(&DETECTION_INPUT, &mut rng);
+            assert_eq!(mode, detect_mode(&output));
+            if mode == AesMode::Ecb {
+                seen_ecb += 1;
+            }
+        }
+        // Both modes should come up a fair amount.
+        assert!(seen_ecb > 400 && seen_ecb < 600);
+    }
 
     #[test]
     fn random_encrypt() {
-        let input = read_to_string("./data/set_1/ch6-solution.txt").unwrap();
+        // Real text doesn't line up repeats on block boundaries, so lead with
+        // the chosen input to make either mode detectable.
+        let mut input = DETECTION_INPUT.to_vec();
+        input.extend_from_slice(read_to_string("./data/set_1/ch6-solution.txt").unwrap().as_bytes());
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut seen = vec![];
         for _ in 0..10 {
-            let output = encryption_oracle(input.as_bytes());
-            // will be true ~50% of the time
-            // Could use a seed to verify guesses
-            let guess = detect_aes_128_ecb(&output);
+            let (output, mode) = encryption_oracle(&input, &mut rng);
+            // The plaintext is padded with 10-20 random bytes
+            assert!(output.len() > input.len() + 10);
+            assert!(output.len() <= input.len() + 20 + 16);
+            assert_eq!(mode, detect_mode(&output));
+            seen.push(mode);
+        }
+        assert!(seen.contains(&AesMode::Ecb) && seen.contains(&AesMode::Cbc));
+    }
+
+    #[test]
+    fn mode_oracle_detect() {
+        let mut oracle = ModeOracle::new(StdRng::seed_from_u64(12));
+        assert_eq!(None, oracle.last_mode());
+        for _ in 0..100 {
+            let ct = oracle.encrypt(&DETECTION_INPUT);
+            assert_eq!(oracle.last_mode(), Some(detect_mode(&ct)));
         }
     }
+
+    #[test]
+    fn mode_oracle_every_mode() {
+        let modes = [AesMode::Ecb, AesMode::Cbc, AesMode::Pcbc, AesMode::Cfb, AesMode::Ofb];
+        let mut oracle = ModeOracle::with_modes(StdRng::seed_from_u64(32), &modes);
+        let mut seen = vec![];
+        for _ in 0..100 {
+            let ct = oracle.encrypt(&DETECTION_INPUT);
+            let mode = oracle.last_mode().unwrap();
+            // Only ECB gives its repeats away, the rest all look alike.
+            assert_eq!(mode == AesMode::Ecb, detect_mode(&ct) == AesMode::Ecb, "{:?}", mode);
+            if !seen.contains(&mode) {
+                seen.push(mode);
+            }
+        }
+        assert_eq!(modes.len(), seen.len());
+    }
+
+    #[test]
+    fn seeded_oracle_is_reproducible() {
+        let a = encryption_oracle(&DETECTION_INPUT, &mut StdRng::seed_from_u64(3));
+        let b = encryption_oracle(&DETECTION_INPUT, &mut StdRng::seed_from_u64(3));
+        assert_eq!(a, b);
+    }
 }
@@ -0,0 +1,238 @@
+//! User profiles stored as `k=v` cookies, `email=foo@bar.com&uid=10&role=user`,
+//! and encrypted with ECB. The encoding is sound, but ECB lets whole blocks of
+//! ciphertext be cut out of one profile and pasted into another.
+
+use rand::Rng;
+
+use crate::modes::Padding;
+
+use super::{aes::{decrypt_aes_128_ecb, encrypt_aes_128_ecb}, oracle::random_key};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub email: String,
+    pub uid: u32,
+    pub role: String,
+}
+
+impl Profile {
+    /// Encode as a `k=v` cookie. Metacharacters in the values are escaped,
+    /// so an email can't add fields of its own.
+    pub fn encode(&self) -> String {
+        encode_kv(&[("email", &self.email), ("uid", &self.uid.to_string()), ("role", &self.role)])
+    }
+
+    /// Parse a `k=v` cookie with exactly the email, uid and role fields.
+    pub fn parse(input: &str) -> Option<Profile> {
+        let mut email = None;
+        let mut uid = None;
+        let mut role = None;
+        for (k, v) in parse_kv(input)? {
+            let field = match k.as_str() {
+                "email" => &mut email,
+                "uid" => &mut uid,
+                "role" => &mut role,
+                _ => return None,
+            };
+            // Let a repeated field win and it could override the real one.
+            if field.replace(v).is_some() {
+                return None;
+            }
+        }
+        Some(Profile { email: email?, uid: uid?.parse().ok()?, role: role? })
+    }
+}
+
+/// The cookie for a new user with the given email.
+pub fn profile_for(email: &str) -> String {
+    Profile { email: email.to_string(), uid: 10, role: "user".to_string() }.encode()
+}
+
+/// Join pairs into `k1=v1&k2=v2`, percent escaping `%`, `&` and `=`.
+pub fn encode_kv(pairs: &[(&str, &str)]) -> String {
+    let mut res = String::new();
+    for (i, (k, v)) in pairs.iter().enumerate() {
+        if i > 0 {
+            res.push('&');
+        }
+        res.push_str(&escape(k));
+        res.push('=');
+        res.push_str(&escape(v));
+    }
+    res
+}
+
+/// Split `k1=v1&k2=v2` into pairs, undoing the escaping from [`encode_kv`].
+/// Returns None if a pair has no `=`, or has more than one, or an escape is
+/// malformed.
+pub fn parse_kv(input: &str) -> Option<Vec<(String, String)>> {
+    if input.is_empty() {
+        return Some(vec![]);
+    }
+    let mut res = vec![];
+    for pair in input.split('&') {
+        let (k, v) = pair.split_once('=')?;
+        if v.contains('=') {
+            return None;
+        }
+        res.push((unescape(k)?, unescape(v)?));
+    }
+    Some(res)
+}
+
+fn escape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '%' => res.push_str("%25"),
+            '&' => res.push_str("%26"),
+            '=' => res.push_str("%3D"),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+fn unescape(s: &str) -> Option<String> {
+    let mut res = Vec::with_capacity(s.len());
+    let mut bytes = s.bytes();
+    while let Some(b) = bytes.next() {
+        if b == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            res.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            res.push(b);
+        }
+    }
+    String::from_utf8(res).ok()
+}
+
+/// Hands out encrypted profiles under a fixed random key, and reads them
+/// back.
+pub struct ProfileOracle {
+    key: Vec<u8>,
+}
+
+impl ProfileOracle {
+    pub fn new<R: Rng>(rng: &mut R) -> ProfileOracle {
+        ProfileOracle { key: random_key(rng) }
+    }
+
+    /// Encrypt `profile_for(email)` with AES 128 ECB.
+    pub fn encrypt_profile(&self, email: &str) -> Vec<u8> {
+        encrypt_aes_128_ecb(profile_for(email).as_bytes(), &self.key, Padding::Pkcs7)
+    }
+
+    /// Decrypt and parse a profile. Returns None if the ciphertext doesn't
+    /// decrypt to a well formed profile.
+    pub fn decrypt_profile(&self, ciphertext: &[u8]) -> Option<Profile> {
+        if !ciphertext.len().is_multiple_of(16) {
+            return None;
+        }
+        let plaintext = decrypt_aes_128_ecb(ciphertext, &self.key, Padding::Pkcs7).ok()?;
+        Profile::parse(std::str::from_utf8(&plaintext).ok()?)
+    }
+}
+
+/// Build a ciphertext that decrypts to a profile with `role=admin`, using
+/// only encrypted profiles from `encrypt_profile`.
+///
+/// One email is sized so `role=` ends a block, leaving `user` alone in the
+/// last block. Another puts `admin` and its PKCS#7 padding in a block of its
+/// own. Splicing that block in place of the last one gives an admin profile.
+/// None of the bytes needed is escaped, so the escaping doesn't help.
+pub fn make_admin<F: FnMut(&str) -> Vec<u8>>(mut encrypt_profile: F) -> Vec<u8> {
+    const PREFIX: usize = "email=".len();
+    const SUFFIX: usize = "&uid=10&role=".len();
+
+    // Fill the first block, then a block of `admin` padded to 16 bytes.
+    let mut email = "A".repeat(16 - PREFIX);
+    email.push_str("admin");
+    email.push_str(&"\x0b".repeat(11));
+    let admin_block = encrypt_profile(&email)[16..32].to_vec();
+
+    // An email that pushes `role=` to the end of a block.
+    let domain = "@bar.com";
+    let mut len = (16 - (PREFIX + SUFFIX) % 16) % 16;
+    while len < domain.len() {
+        len += 16;
+    }
+    let email = format!("{}{}", "f".repeat(len - domain.len()), domain);
+    let ct = encrypt_profile(&email);
+    let keep = (PREFIX + email.len() + SUFFIX) / 16 * 16;
+    [&ct[..keep], &admin_block].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    #[test]
+    fn parse_kv_happy() {
+        let expected = vec![
+            ("foo".to_string(), "bar".to_string()),
+            ("baz".to_string(), "qux".to_string()),
+            ("zap".to_string(), "zazzle".to_string()),
+        ];
+        assert_eq!(Some(expected), parse_kv("foo=bar&baz=qux&zap=zazzle"));
+    }
+
+    #[test]
+    fn parse_kv_malformed() {
+        assert_eq!(None, parse_kv("foo=bar&baz"));
+        assert_eq!(None, parse_kv("foo=bar=baz"));
+        assert_eq!(None, parse_kv("foo=%2"));
+        assert_eq!(None, parse_kv("foo=%zz"));
+    }
+
+    #[test]
+    fn encode_kv_round_trip() {
+        let pairs = [("a&b", "c=d"), ("e", "100%"), ("f", "")];
+        let encoded = encode_kv(&pairs);
+        assert_eq!("a%26b=c%3Dd&e=100%25&f=", encoded);
+        let parsed = parse_kv(&encoded).unwrap();
+        let parsed: Vec<(&str, &str)> = parsed.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        assert_eq!(pairs.as_slice(), parsed);
+    }
+
+    #[test]
+    fn profile_for_happy() {
+        assert_eq!("email=foo@bar.com&uid=10&role=user", profile_for("foo@bar.com"));
+    }
+
+    #[test]
+    fn profile_for_rejects_injection() {
+        let email = "foo@bar.com&role=admin";
+        let cookie = profile_for(email);
+        assert_eq!("email=foo@bar.com%26role%3Dadmin&uid=10&role=user", cookie);
+        let profile = Profile::parse(&cookie).unwrap();
+        assert_eq!(email, profile.email);
+        assert_eq!("user", profile.role);
+
+        let oracle = ProfileOracle::new(&mut StdRng::seed_from_u64(13));
+        let profile = oracle.decrypt_profile(&oracle.encrypt_profile(email)).unwrap();
+        assert_eq!("user", profile.role);
+    }
+
+    #[test]
+    fn profile_parse_rejects_repeated_fields() {
+        assert_eq!(None, Profile::parse("email=a&uid=10&role=user&role=admin"));
+        assert_eq!(None, Profile::parse("email=a&uid=10"));
+        assert_eq!(None, Profile::parse("email=a&uid=ten&role=user"));
+    }
+
+    // S2C13
+    #[test]
+    fn make_admin_happy() {
+        let mut rng = StdRng::seed_from_u64(1313);
+        for _ in 0..10 {
+            let oracle = ProfileOracle::new(&mut rng);
+            let ct = make_admin(|email| oracle.encrypt_profile(email));
+            let profile = oracle.decrypt_profile(&ct).unwrap();
+            assert_eq!("admin", profile.role);
+            assert_eq!(10, profile.uid);
+        }
+    }
+}
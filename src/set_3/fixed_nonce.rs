@@ -0,0 +1,133 @@
+//! Breaking CTR when the nonce is reused. Every message is XORed with the
+//! same keystream, so lining the ciphertexts up makes each column a single
+//! byte XOR, and the whole set is repeating key XOR with a key as long as
+//! the messages.
+
+use crate::set_1::{single_byte_cipher::solve_one_byte_cipher, xor::break_key_repeat_xor_keysize};
+
+/// Recover the keystream by truncating every ciphertext to the shortest one
+/// and breaking the lot as repeating key XOR, with the keysize being that
+/// length. Returns as much keystream as the shortest ciphertext covers.
+pub fn break_fixed_nonce_truncated(ciphertexts: &[Vec<u8>]) -> Vec<u8> {
+    let len = match ciphertexts.iter().map(|c| c.len()).min() {
+        Some(len) if len > 0 => len,
+        _ => return vec![],
+    };
+    let joined: Vec<u8> = ciphertexts.iter().flat_map(|c| &c[..len]).copied().collect();
+    break_key_repeat_xor_keysize(&joined, len)
+}
+
+/// Recover the keystream one column at a time, carrying on past the
+/// shortest ciphertext with whichever ciphertexts are still long enough.
+/// Returns keystream as long as the longest ciphertext, though the later
+/// columns have fewer bytes to go on and are less reliable.
+pub fn break_fixed_nonce_columns(ciphertexts: &[Vec<u8>]) -> Vec<u8> {
+    let len = ciphertexts.iter().map(|c| c.len()).max().unwrap_or(0);
+    let mut keystream = Vec::with_capacity(len);
+    for i in 0..len {
+        let column: Vec<u8> = ciphertexts.iter().filter_map(|c| c.get(i)).copied().collect();
+        keystream.push(solve_one_byte_cipher(&column).1);
+    }
+    keystream
+}
+
+/// Correct the keystream from a guess at part of one plaintext, the
+/// substitution step for columns statistics got wrong. `known` is the
+/// plaintext believed to be at `offset` in `ciphertext`.
+pub fn fix_keystream(keystream: &mut Vec<u8>, ciphertext: &[u8], offset: usize, known: &[u8]) {
+    let end = offset + known.len();
+    assert!(end <= ciphertext.len(), "guess runs past the end of the ciphertext");
+    if keystream.len() < end {
+        keystream.resize(end, 0);
+    }
+    for (i, k) in known.iter().enumerate() {
+        keystream[offset + i] = ciphertext[offset + i] ^ k;
+    }
+}
+
+/// Decrypt as much of the ciphertext as the keystream covers.
+pub fn apply_keystream(ciphertext: &[u8], keystream: &[u8]) -> Vec<u8> {
+    ciphertext.iter().zip(keystream).map(|(c, k)| c ^ k).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use crate::{byte_util::base64_decode, set_2::{aes::encrypt_aes_128_ctr, oracle::random_key}};
+
+    use super::*;
+
+    fn encrypt_lines(plaintexts: &[Vec<u8>], seed: u64) -> Vec<Vec<u8>> {
+        let key = random_key(&mut StdRng::seed_from_u64(seed));
+        plaintexts.iter().map(|p| encrypt_aes_128_ctr(p, &key, 0)).collect()
+    }
+
+    fn read_lines(path: &str) -> Vec<Vec<u8>> {
+        read_to_string(path).unwrap().lines().map(|l| l.as_bytes().to_vec()).collect()
+    }
+
+    fn read_base64_lines(path: &str) -> Vec<Vec<u8>> {
+        read_lines(path).iter().map(|l| base64_decode(l)).collect()
+    }
+
+    /// The fraction of plaintext bytes recovered correctly, only counting
+    /// bytes the keystream covers.
+    fn accuracy(plaintexts: &[Vec<u8>], ciphertexts: &[Vec<u8>], keystream: &[u8]) -> f64 {
+        let mut right = 0;
+        let mut total = 0;
+        for (p, c) in plaintexts.iter().zip(ciphertexts) {
+            let actual = apply_keystream(c, keystream);
+            right += actual.iter().zip(p).filter(|(a, b)| a == b).count();
+            total += actual.len();
+        }
+        right as f64 / total as f64
+    }
+
+    // The approach of S3C20, over the lines of the challenge 6 plaintext.
+    #[test]
+    fn fixed_nonce_truncated() {
+        let plaintexts: Vec<Vec<u8>> = read_lines("./data/set_1/ch6-solution.txt").into_iter().filter(|l| l.len() >= 20).collect();
+        let ciphertexts = encrypt_lines(&plaintexts, 20);
+        let keystream = break_fixed_nonce_truncated(&ciphertexts);
+        assert_eq!(plaintexts.iter().map(|p| p.len()).min().unwrap(), keystream.len());
+        assert!(accuracy(&plaintexts, &ciphertexts, &keystream) > 0.9);
+    }
+
+    // S3C19
+    #[test]
+    fn fixed_nonce_columns() {
+        let plaintexts = read_base64_lines("./data/set_3/ch19.txt");
+        let ciphertexts = encrypt_lines(&plaintexts, 19);
+        let truncated = break_fixed_nonce_truncated(&ciphertexts);
+        let mut keystream = break_fixed_nonce_columns(&ciphertexts);
+        assert_eq!(plaintexts.iter().map(|p| p.len()).max().unwrap(), keystream.len());
+        assert!(keystream.len() > truncated.len());
+        // Statistics get most of it, though the tail is thin.
+        let before = accuracy(&plaintexts, &ciphertexts, &keystream);
+        assert!(before > 0.9);
+
+        // Only one line reaches the last columns. Recognising it from the
+        // part that decrypted and substituting the known line in fixes them.
+        let longest = plaintexts.iter().position(|p| p.len() == keystream.len()).unwrap();
+        let known = "He, too, has been changed in his turn,".as_bytes();
+        fix_keystream(&mut keystream, &ciphertexts[longest], 0, known);
+        assert_eq!(known, apply_keystream(&ciphertexts[longest], &keystream));
+        assert!(accuracy(&plaintexts, &ciphertexts, &keystream) > before);
+    }
+
+    #[test]
+    fn fix_keystream_extends() {
+        let mut keystream = vec![1, 2];
+        fix_keystream(&mut keystream, &[5, 5, 5, 5], 2, &[4, 4]);
+        assert_eq!(vec![1, 2, 1, 1], keystream);
+    }
+
+    #[test]
+    fn fixed_nonce_empty() {
+        assert!(break_fixed_nonce_truncated(&[]).is_empty());
+        assert!(break_fixed_nonce_columns(&[]).is_empty());
+    }
+}
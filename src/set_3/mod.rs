@@ -0,0 +1,6 @@
+pub mod fixed_nonce;
+pub mod mt19937;
+pub mod mt_cipher;
+pub mod mt_clone;
+pub mod mt_seed;
+pub mod padding_oracle;
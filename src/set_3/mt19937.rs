@@ -0,0 +1,316 @@
+//! The MT19937 Mersenne Twister, in its 32 and 64 bit variants, following
+//! the reference implementations by Matsumoto and Nishimura. Both implement
+//! [`RngCore`] so they can stand in anywhere the `rand` engines are used.
+
+use rand::{Error, RngCore, SeedableRng};
+
+pub const N: usize = 624;
+pub const M: usize = 397;
+pub const MATRIX_A: u32 = 0x9908b0df;
+const UPPER_MASK: u32 = 0x80000000;
+const LOWER_MASK: u32 = 0x7fffffff;
+
+/// The 32 bit Mersenne Twister.
+#[derive(Clone)]
+pub struct Mt19937 {
+    mt: [u32; N],
+    index: usize,
+}
+
+impl Mt19937 {
+    /// Seed as `init_genrand` does. 5489 is the reference default.
+    pub fn new(seed: u32) -> Mt19937 {
+        let mut mt = [0u32; N];
+        mt[0] = seed;
+        for i in 1..N {
+            mt[i] = 1812433253u32.wrapping_mul(mt[i - 1] ^ (mt[i - 1] >> 30)).wrapping_add(i as u32);
+        }
+        Mt19937 { mt, index: N }
+    }
+
+    /// Seed from an array as `init_by_array` does, which is how Python's
+    /// `random.seed` seeds the generator. Panics if the key is empty.
+    pub fn from_key(key: &[u32]) -> Mt19937 {
+        assert!(!key.is_empty(), "MT19937 key must not be empty");
+        let mut res = Mt19937::new(19650218);
+        let mt = &mut res.mt;
+        let (mut i, mut j) = (1, 0);
+        for _ in 0..N.max(key.len()) {
+            let prev = mt[i - 1] ^ (mt[i - 1] >> 30);
+            mt[i] = (mt[i] ^ prev.wrapping_mul(1664525)).wrapping_add(key[j]).wrapping_add(j as u32);
+            i += 1;
+            j += 1;
+            if i >= N {
+                mt[0] = mt[N - 1];
+                i = 1;
+            }
+            if j >= key.len() {
+                j = 0;
+            }
+        }
+        for _ in 0..N - 1 {
+            let prev = mt[i - 1] ^ (mt[i - 1] >> 30);
+            mt[i] = (mt[i] ^ prev.wrapping_mul(1566083941)).wrapping_sub(i as u32);
+            i += 1;
+            if i >= N {
+                mt[0] = mt[N - 1];
+                i = 1;
+            }
+        }
+        mt[0] = 0x80000000;
+        res
+    }
+
+    /// Build a generator from its raw state words and the index of the next
+    /// word to output. An index of 624 means the state is used up and the
+    /// next output twists first.
+    pub fn from_state(state: [u32; N], index: usize) -> Mt19937 {
+        assert!(index <= N, "index must be at most {}", N);
+        Mt19937 { mt: state, index }
+    }
+
+    fn twist(&mut self) {
+        for i in 0..N {
+            let y = (self.mt[i] & UPPER_MASK) | (self.mt[(i + 1) % N] & LOWER_MASK);
+            let mag = if y & 1 == 0 { 0 } else { MATRIX_A };
+            self.mt[i] = self.mt[(i + M) % N] ^ (y >> 1) ^ mag;
+        }
+        self.index = 0;
+    }
+}
+
+/// The output tempering applied to each state word.
+pub fn temper(mut y: u32) -> u32 {
+    y ^= y >> 11;
+    y ^= (y << 7) & 0x9d2c5680;
+    y ^= (y << 15) & 0xefc60000;
+    y ^= y >> 18;
+    y
+}
+
+impl RngCore for Mt19937 {
+    fn next_u32(&mut self) -> u32 {
+        if self.index >= N {
+            self.twist();
+        }
+        let y = self.mt[self.index];
+        self.index += 1;
+        temper(y)
+    }
+
+    /// Two outputs, low word first, as `rand_core` combines them.
+    fn next_u64(&mut self) -> u64 {
+        let lo = self.next_u32() as u64;
+        let hi = self.next_u32() as u64;
+        (hi << 32) | lo
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Mt19937 {
+    type Seed = [u8; 4];
+
+    /// The seed is a little endian `u32` passed to [`Mt19937::new`].
+    fn from_seed(seed: [u8; 4]) -> Mt19937 {
+        Mt19937::new(u32::from_le_bytes(seed))
+    }
+}
+
+const N64: usize = 312;
+const M64: usize = 156;
+const MATRIX_A64: u64 = 0xb5026f5aa96619e9;
+const UPPER_MASK64: u64 = 0xffffffff80000000;
+const LOWER_MASK64: u64 = 0x7fffffff;
+
+/// The 64 bit Mersenne Twister, MT19937-64.
+#[derive(Clone)]
+pub struct Mt19937_64 {
+    mt: [u64; N64],
+    index: usize,
+}
+
+impl Mt19937_64 {
+    /// Seed as `init_genrand64` does. 5489 is the reference default.
+    pub fn new(seed: u64) -> Mt19937_64 {
+        let mut mt = [0u64; N64];
+        mt[0] = seed;
+        for i in 1..N64 {
+            mt[i] = 6364136223846793005u64.wrapping_mul(mt[i - 1] ^ (mt[i - 1] >> 62)).wrapping_add(i as u64);
+        }
+        Mt19937_64 { mt, index: N64 }
+    }
+
+    /// Seed from an array as `init_by_array64` does. Panics if the key is
+    /// empty.
+    pub fn from_key(key: &[u64]) -> Mt19937_64 {
+        assert!(!key.is_empty(), "MT19937-64 key must not be empty");
+        let mut res = Mt19937_64::new(19650218);
+        let mt = &mut res.mt;
+        let (mut i, mut j) = (1, 0);
+        for _ in 0..N64.max(key.len()) {
+            let prev = mt[i - 1] ^ (mt[i - 1] >> 62);
+            mt[i] = (mt[i] ^ prev.wrapping_mul(3935559000370003845)).wrapping_add(key[j]).wrapping_add(j as u64);
+            i += 1;
+            j += 1;
+            if i >= N64 {
+                mt[0] = mt[N64 - 1];
+                i = 1;
+            }
+            if j >= key.len() {
+                j = 0;
+            }
+        }
+        for _ in 0..N64 - 1 {
+            let prev = mt[i - 1] ^ (mt[i - 1] >> 62);
+            mt[i] = (mt[i] ^ prev.wrapping_mul(2862933555777941757)).wrapping_sub(i as u64);
+            i += 1;
+            if i >= N64 {
+                mt[0] = mt[N64 - 1];
+                i = 1;
+            }
+        }
+        mt[0] = 1 << 63;
+        res
+    }
+
+    fn twist(&mut self) {
+        for i in 0..N64 {
+            let y = (self.mt[i] & UPPER_MASK64) | (self.mt[(i + 1) % N64] & LOWER_MASK64);
+            let mag = if y & 1 == 0 { 0 } else { MATRIX_A64 };
+            self.mt[i] = self.mt[(i + M64) % N64] ^ (y >> 1) ^ mag;
+        }
+        self.index = 0;
+    }
+}
+
+impl RngCore for Mt19937_64 {
+    /// The low half of the next output.
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        if self.index >= N64 {
+            self.twist();
+        }
+        let mut y = self.mt[self.index];
+        self.index += 1;
+        y ^= (y >> 29) & 0x5555555555555555;
+        y ^= (y << 17) & 0x71d67fffeda60000;
+        y ^= (y << 37) & 0xfff7eee000000000;
+        y ^= y >> 43;
+        y
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Mt19937_64 {
+    type Seed = [u8; 8];
+
+    /// The seed is a little endian `u64` passed to [`Mt19937_64::new`].
+    fn from_seed(seed: [u8; 8]) -> Mt19937_64 {
+        Mt19937_64::new(u64::from_le_bytes(seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    // The C++ standard requires the 10000th output of a default constructed
+    // std::mt19937 and std::mt19937_64 to be these.
+    #[test]
+    fn mt19937_default_seed() {
+        let mut mt = Mt19937::new(5489);
+        assert_eq!(3499211612, mt.next_u32());
+        assert_eq!(4123659995, (1..10000).map(|_| mt.next_u32()).last().unwrap());
+    }
+
+    // mt19937ar.out, the reference output for init_by_array.
+    #[test]
+    fn mt19937_by_array() {
+        let mut mt = Mt19937::from_key(&[0x123, 0x234, 0x345, 0x456]);
+        let expected = [1067595299, 955945823, 477289528, 4107218783, 4228976476];
+        for e in expected {
+            assert_eq!(e, mt.next_u32());
+        }
+    }
+
+    // Python seeds its Mersenne Twister with init_by_array, so
+    // `random.seed(5489); random.getrandbits(32)` gives these.
+    #[test]
+    fn mt19937_matches_python() {
+        let mut mt = Mt19937::from_key(&[5489]);
+        assert_eq!([3382763572, 956215839, 417760592], [mt.next_u32(), mt.next_u32(), mt.next_u32()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "key must not be empty")]
+    fn mt19937_empty_key() {
+        Mt19937::from_key(&[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "key must not be empty")]
+    fn mt19937_64_empty_key() {
+        Mt19937_64::from_key(&[]);
+    }
+
+    #[test]
+    fn mt19937_64_default_seed() {
+        let mut mt = Mt19937_64::new(5489);
+        assert_eq!(14514284786278117030, mt.next_u64());
+        assert_eq!(9981545732273789042, (1..10000).map(|_| mt.next_u64()).last().unwrap());
+    }
+
+    // mt19937-64.out, the reference output for init_by_array64.
+    #[test]
+    fn mt19937_64_by_array() {
+        let mut mt = Mt19937_64::from_key(&[0x12345, 0x23456, 0x34567, 0x45678]);
+        let expected = [7266447313870364031, 4946485549665804864, 16945909448695747420, 16394063075524226720, 4873882236456199058];
+        for e in expected {
+            assert_eq!(e, mt.next_u64());
+        }
+    }
+
+    #[test]
+    fn mt19937_rng_core() {
+        let mut mt = Mt19937::from_seed(5489u32.to_le_bytes());
+        let mut bytes = [0u8; 6];
+        mt.fill_bytes(&mut bytes);
+        let mut reference = Mt19937::new(5489);
+        let first = reference.next_u32().to_le_bytes();
+        let second = reference.next_u32().to_le_bytes();
+        assert_eq!([first[0], first[1], first[2], first[3], second[0], second[1]], bytes);
+        // Usable through the rand traits.
+        let roll: u8 = Mt19937::new(1).gen_range(1..=6);
+        assert!((1..=6).contains(&roll));
+
+        let mut mt = Mt19937_64::from_seed(5489u64.to_le_bytes());
+        assert_eq!(14514284786278117030, mt.next_u64());
+    }
+}
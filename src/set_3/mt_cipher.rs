@@ -0,0 +1,99 @@
+//! A stream cipher with MT19937 as the keystream. The key is only 16 bits,
+//! so any known plaintext gives it away to brute force, and tokens made the
+//! same way with the time as the seed are no better.
+
+use rand::{Rng, RngCore};
+
+use super::{mt19937::Mt19937, mt_seed::Clock};
+
+/// The bytes of a reset token.
+pub const TOKEN_LEN: usize = 16;
+
+/// XOR with the output of an MT19937 seeded with the key, four little
+/// endian bytes per output. Encrypting and decrypting are the same.
+pub fn mt_cipher(input: &[u8], key: u16) -> Vec<u8> {
+    let mut keystream = vec![0; input.len()];
+    Mt19937::new(key as u32).fill_bytes(&mut keystream);
+    input.iter().zip(keystream).map(|(i, k)| i ^ k).collect()
+}
+
+/// Encrypt the plaintext behind 5 to 40 random bytes.
+pub fn encrypt_with_random_prefix<R: Rng>(plaintext: &[u8], key: u16, rng: &mut R) -> Vec<u8> {
+    let mut input = vec![0; rng.gen_range(5..=40)];
+    rng.fill_bytes(&mut input);
+    input.extend_from_slice(plaintext);
+    mt_cipher(&input, key)
+}
+
+/// Recover the key from a ciphertext ending in `known`, by trying all of
+/// them. Returns None if `known` is empty, as every key would match.
+pub fn recover_mt_key(ciphertext: &[u8], known: &[u8]) -> Option<u16> {
+    if known.is_empty() {
+        return None;
+    }
+    let offset = ciphertext.len().checked_sub(known.len())?;
+    let mut keystream = vec![0; ciphertext.len()];
+    (0..=u16::MAX).find(|&key| {
+        Mt19937::new(key as u32).fill_bytes(&mut keystream);
+        ciphertext[offset..].iter().zip(&keystream[offset..]).zip(known).all(|((c, k), p)| c ^ k == *p)
+    })
+}
+
+/// A password reset token, from an MT19937 seeded with the current time.
+pub fn password_reset_token<C: Clock>(clock: &C) -> [u8; TOKEN_LEN] {
+    let mut token = [0; TOKEN_LEN];
+    Mt19937::new(clock.now()).fill_bytes(&mut token);
+    token
+}
+
+/// Check whether a token came from an MT19937 seeded with a timestamp
+/// between `earliest` and `latest`, inclusive. Returns the timestamp if so.
+pub fn detect_time_seeded_token(token: &[u8], earliest: u32, latest: u32) -> Option<u32> {
+    let mut expected = vec![0; token.len()];
+    (earliest..=latest).rev().find(|&seed| {
+        Mt19937::new(seed).fill_bytes(&mut expected);
+        expected == token
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::set_3::mt_seed::SimulatedClock;
+
+    use super::*;
+
+    #[test]
+    fn mt_cipher_round_trip() {
+        let plaintext = b"Ice ice baby, too cold";
+        let ciphertext = mt_cipher(plaintext, 0xbeef);
+        assert_ne!(&plaintext[..], &ciphertext[..]);
+        assert_eq!(&plaintext[..], &mt_cipher(&ciphertext, 0xbeef)[..]);
+    }
+
+    // S3C24
+    #[test]
+    fn recover_mt_key_happy() {
+        let mut rng = StdRng::seed_from_u64(24);
+        let key = rng.gen();
+        let known = [b'A'; 14];
+        let ciphertext = encrypt_with_random_prefix(&known, key, &mut rng);
+        assert_eq!(Some(key), recover_mt_key(&ciphertext, &known));
+        assert_eq!(None, recover_mt_key(&known, &[0; 15]));
+        assert_eq!(None, recover_mt_key(&ciphertext, &[]));
+    }
+
+    #[test]
+    fn detect_time_seeded_token_happy() {
+        let mut clock = SimulatedClock::new(1_700_000_000);
+        let token = password_reset_token(&clock);
+        clock.sleep(500);
+        let now = clock.now();
+        assert_eq!(Some(1_700_000_000), detect_time_seeded_token(&token, now - 3600, now));
+
+        let mut random = [0; TOKEN_LEN];
+        StdRng::seed_from_u64(24).fill_bytes(&mut random);
+        assert_eq!(None, detect_time_seeded_token(&random, now - 3600, now));
+    }
+}
@@ -0,0 +1,276 @@
+//! Cloning an MT19937 generator from its output. The tempering is
+//! invertible, and the state is just the last 624 untempered outputs, so
+//! 624 outputs in a row give away every output to come.
+//!
+//! When only some bits of each output are seen, or the outputs seen aren't
+//! consecutive, there's nothing to untemper. Everything MT19937 does is
+//! linear over GF(2) though, so each bit seen is a linear equation in the
+//! bits of the state, and enough of them can be solved for it.
+
+use super::mt19937::{temper, Mt19937, M, MATRIX_A, N};
+
+/// Invert [`temper`], recovering the state word an output came from.
+pub fn untemper(mut y: u32) -> u32 {
+    y = undo_right_shift(y, 18);
+    y = undo_left_shift(y, 15, 0xefc60000);
+    y = undo_left_shift(y, 7, 0x9d2c5680);
+    undo_right_shift(y, 11)
+}
+
+/// Invert `y ^= y >> shift`. Each pass fixes another `shift` bits, from the
+/// top down.
+fn undo_right_shift(y: u32, shift: u32) -> u32 {
+    let mut res = y;
+    for _ in 0..32 / shift {
+        res = y ^ (res >> shift);
+    }
+    res
+}
+
+/// Invert `y ^= (y << shift) & mask`, fixing `shift` bits a pass from the
+/// bottom up.
+fn undo_left_shift(y: u32, shift: u32, mask: u32) -> u32 {
+    let mut res = y;
+    for _ in 0..32 / shift {
+        res = y ^ ((res << shift) & mask);
+    }
+    res
+}
+
+/// Clone a generator from any 624 consecutive outputs. They don't need to
+/// line up with a twist: every output is untempered from a word made by the
+/// same recurrence on the 624 words before it, so those words are a state
+/// that carries on from the last output given.
+pub fn clone_mt19937(outputs: &[u32]) -> Mt19937 {
+    assert_eq!(N, outputs.len(), "cloning needs {} consecutive outputs", N);
+    let mut state = [0u32; N];
+    for (s, &o) in state.iter_mut().zip(outputs) {
+        *s = untemper(o);
+    }
+    Mt19937::from_state(state, N)
+}
+
+/// Some of the bits of one output. `index` counts outputs from the first
+/// one the state is recovered for, and `mask` picks out the bits of `value`
+/// that were seen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Observation {
+    pub index: usize,
+    pub mask: u32,
+    pub value: u32,
+}
+
+impl Observation {
+    /// The top `bits` bits of an output, as left by `output >> (32 - bits)`.
+    pub fn top_bits(index: usize, bits: u32, truncated: u32) -> Observation {
+        assert!(bits > 0 && bits <= 32, "between 1 and 32 bits can be seen");
+        let shift = 32 - bits;
+        Observation { index, mask: u32::MAX << shift, value: truncated << shift }
+    }
+}
+
+/// The number of state bits, 32 for each of the 624 words.
+const VARS: usize = N * 32;
+/// The low 31 bits of the first word only show up in output 0, and never
+/// feed into the twist.
+const UNUSED_BITS: usize = 31;
+
+/// A set of state bits, standing for their XOR.
+#[derive(Clone)]
+struct Bits(Box<[u64; VARS / 64]>);
+
+impl Bits {
+    fn zero() -> Bits {
+        Bits(Box::new([0; VARS / 64]))
+    }
+
+    fn unit(var: usize) -> Bits {
+        let mut res = Bits::zero();
+        res.0[var / 64] |= 1 << (var % 64);
+        res
+    }
+
+    fn xor(&mut self, other: &Bits) {
+        for (a, b) in self.0.iter_mut().zip(other.0.iter()) {
+            *a ^= b;
+        }
+    }
+
+    fn lowest(&self) -> Option<usize> {
+        self.0.iter().enumerate().find(|(_, &w)| w != 0).map(|(i, w)| i * 64 + w.trailing_zeros() as usize)
+    }
+
+    fn get(&self, var: usize) -> bool {
+        self.0[var / 64] >> (var % 64) & 1 == 1
+    }
+
+    /// The XOR of the bits in both sets.
+    fn parity(&self, other: &Bits) -> bool {
+        self.0.iter().zip(other.0.iter()).fold(0, |acc, (a, b)| acc ^ (a & b).count_ones()) & 1 == 1
+    }
+}
+
+/// One state word, with each bit as the XOR of initial state bits.
+type Word = Vec<Bits>;
+
+/// Recover the generator state from scattered bits of its output, by
+/// Gaussian elimination over GF(2).
+///
+/// The returned generator is positioned at output 1, so it replays the
+/// observed outputs after the first and carries on from there. Output 0 is
+/// skipped as only the bits of it that were seen can be known. Returns None
+/// if the observations don't pin down all 19937 bits of state that matter,
+/// or contradict each other. Solving needs around a hundred megabytes of
+/// memory and is slow outside a release build.
+pub fn recover_mt19937(observations: &[Observation]) -> Option<Mt19937> {
+    let mut observations = observations.to_vec();
+    observations.sort_by_key(|o| o.index);
+
+    // Which state bits each output bit is the XOR of.
+    let tempering: Vec<u32> = (0..32).map(|j| (0..32).filter(|k| temper(1 << k) >> j & 1 == 1).fold(0, |m, k| m | 1 << k)).collect();
+
+    // The last 624 symbolic words, x_n kept at n % 624.
+    let mut window: Vec<Word> = (0..N).map(|n| (0..32).map(|b| Bits::unit(n * 32 + b)).collect()).collect();
+    let mut next = N;
+    let mut basis: Vec<Option<(Bits, bool)>> = vec![None; VARS];
+
+    for o in observations {
+        while next <= o.index {
+            let word = twist_word(&window, next);
+            window[next % N] = word;
+            next += 1;
+        }
+        let word = &window[o.index % N];
+        for j in (0..32).filter(|j| o.mask >> j & 1 == 1) {
+            let mut row = Bits::zero();
+            for k in (0..32).filter(|k| tempering[j] >> k & 1 == 1) {
+                row.xor(&word[k]);
+            }
+            let mut rhs = o.value >> j & 1 == 1;
+            loop {
+                let Some(pivot) = row.lowest() else {
+                    if rhs {
+                        return None;
+                    }
+                    break;
+                };
+                match &basis[pivot] {
+                    Some((b, b_rhs)) => {
+                        row.xor(b);
+                        rhs ^= b_rhs;
+                    }
+                    None => {
+                        basis[pivot] = Some((row, rhs));
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    // Each row's pivot is its lowest bit, so with every bit past the unused
+    // ones a pivot, solving from the top down never depends on a guess.
+    if basis[UNUSED_BITS..].iter().any(|b| b.is_none()) {
+        return None;
+    }
+    let mut solution = Bits::zero();
+    for var in (0..VARS).rev() {
+        if let Some((row, rhs)) = &basis[var] {
+            if rhs ^ row.parity(&solution) {
+                solution.0[var / 64] |= 1 << (var % 64);
+            }
+        }
+    }
+    let mut state = [0u32; N];
+    for (n, s) in state.iter_mut().enumerate() {
+        *s = (0..32).filter(|b| solution.get(n * 32 + b)).fold(0, |w, b| w | 1 << b);
+    }
+    Some(Mt19937::from_state(state, 1))
+}
+
+/// Symbolic x_n from x_{n-624}, x_{n-623} and x_{n-227}, the recurrence
+/// the twist computes.
+fn twist_word(window: &[Word], n: usize) -> Word {
+    let upper = &window[n % N];
+    let lower = &window[(n + 1) % N];
+    let mid = &window[(n + M) % N];
+    // y is the top bit of x_{n-624} and the rest of x_{n-623}.
+    let y = |b: usize| if b == 31 { &upper[31] } else { &lower[b] };
+    (0..32)
+        .map(|b| {
+            let mut bit = mid[b].clone();
+            if b < 31 {
+                bit.xor(y(b + 1));
+            }
+            if MATRIX_A >> b & 1 == 1 {
+                bit.xor(y(0));
+            }
+            bit
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::RngCore;
+
+    use super::*;
+
+    #[test]
+    fn untemper_happy() {
+        for y in [0, 1, 0x80000000, 0xdeadbeef, u32::MAX, 3499211612] {
+            assert_eq!(y, untemper(temper(y)));
+            assert_eq!(y, temper(untemper(y)));
+        }
+    }
+
+    // S3C23
+    #[test]
+    fn clone_mt19937_happy() {
+        let mut mt = Mt19937::new(23);
+        let outputs: Vec<u32> = (0..N).map(|_| mt.next_u32()).collect();
+        let mut clone = clone_mt19937(&outputs);
+        for _ in 0..2000 {
+            assert_eq!(mt.next_u32(), clone.next_u32());
+        }
+    }
+
+    #[test]
+    fn clone_mt19937_unaligned() {
+        let mut mt = Mt19937::new(230);
+        for _ in 0..100 {
+            mt.next_u32();
+        }
+        let outputs: Vec<u32> = (0..N).map(|_| mt.next_u32()).collect();
+        let mut clone = clone_mt19937(&outputs);
+        for _ in 0..2000 {
+            assert_eq!(mt.next_u32(), clone.next_u32());
+        }
+    }
+
+    #[test]
+    fn recover_mt19937_truncated_and_unaligned() {
+        // Only the top 16 bits of each output, starting part way through a
+        // state array.
+        let mut mt = Mt19937::new(2323);
+        for _ in 0..300 {
+            mt.next_u32();
+        }
+        let start = mt.clone();
+        let outputs: Vec<u32> = (0..1400).map(|_| mt.next_u32()).collect();
+        let observations: Vec<Observation> = outputs.iter().enumerate().map(|(i, o)| Observation::top_bits(i, 16, o >> 16)).collect();
+
+        let mut recovered = recover_mt19937(&observations).unwrap();
+        let mut expected = start;
+        expected.next_u32();
+        for _ in 0..3000 {
+            assert_eq!(expected.next_u32(), recovered.next_u32());
+        }
+    }
+
+    #[test]
+    fn recover_mt19937_needs_enough_bits() {
+        let mut mt = Mt19937::new(1);
+        let observations: Vec<Observation> = (0..100).map(|i| Observation::top_bits(i, 8, mt.next_u32() >> 24)).collect();
+        assert!(recover_mt19937(&observations).is_none());
+    }
+}
@@ -0,0 +1,105 @@
+//! Recovering the seed of an MT19937 seeded from the clock. A UNIX
+//! timestamp only has so many values near the time a token was made, so
+//! trying each one as a seed finds it in no time.
+
+use std::{
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use rand::{Rng, RngCore};
+
+use super::mt19937::Mt19937;
+
+/// Where the time comes from, so waiting can be simulated.
+pub trait Clock {
+    /// Seconds since the UNIX epoch.
+    fn now(&self) -> u32;
+    fn sleep(&mut self, secs: u32);
+}
+
+/// The real clock, which really sleeps.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u32 {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).expect("clock is before 1970").as_secs();
+        u32::try_from(secs).expect("time is past 2106 and doesn't fit a 32 bit seed")
+    }
+
+    fn sleep(&mut self, secs: u32) {
+        thread::sleep(Duration::from_secs(secs as u64));
+    }
+}
+
+/// A clock that only moves when slept on.
+pub struct SimulatedClock {
+    now: u32,
+}
+
+impl SimulatedClock {
+    pub fn new(now: u32) -> SimulatedClock {
+        SimulatedClock { now }
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> u32 {
+        self.now
+    }
+
+    fn sleep(&mut self, secs: u32) {
+        self.now = self.now.checked_add(secs).expect("simulated clock slept past 2106");
+    }
+}
+
+/// Wait a random 40 to 1000 seconds, seed with the time, wait again and
+/// return the first output.
+pub fn wait_and_seed<C: Clock, R: Rng>(clock: &mut C, rng: &mut R) -> u32 {
+    clock.sleep(rng.gen_range(40..=1000));
+    let mut mt = Mt19937::new(clock.now());
+    clock.sleep(rng.gen_range(40..=1000));
+    mt.next_u32()
+}
+
+/// Find the timestamp between `earliest` and `latest`, inclusive, that
+/// seeds a generator whose first output is `output`. The latest times are
+/// tried first, as tokens are usually cracked soon after they're made.
+pub fn crack_timestamp_seed(output: u32, earliest: u32, latest: u32) -> Option<u32> {
+    (earliest..=latest).rev().find(|&seed| Mt19937::new(seed).next_u32() == output)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    // S3C22
+    #[test]
+    fn crack_timestamp_seed_happy() {
+        let start = 1_700_000_000;
+        let mut clock = SimulatedClock::new(start);
+        let output = wait_and_seed(&mut clock, &mut StdRng::seed_from_u64(22));
+        let now = clock.now();
+        assert!(now >= start + 80 && now <= start + 2000);
+
+        let seed = crack_timestamp_seed(output, now - 2000, now).unwrap();
+        assert_eq!(output, Mt19937::new(seed).next_u32());
+        assert!(seed > start && seed < now);
+    }
+
+    #[test]
+    #[should_panic(expected = "slept past 2106")]
+    fn simulated_clock_overflow() {
+        SimulatedClock::new(u32::MAX - 10).sleep(11);
+    }
+
+    #[test]
+    fn crack_timestamp_seed_window() {
+        let output = Mt19937::new(1000).next_u32();
+        assert_eq!(Some(1000), crack_timestamp_seed(output, 1000, 1000));
+        assert_eq!(Some(1000), crack_timestamp_seed(output, 900, 1100));
+        assert_eq!(None, crack_timestamp_seed(output, 1001, 1100));
+    }
+}
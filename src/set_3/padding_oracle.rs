@@ -0,0 +1,195 @@
+//! The CBC padding oracle attack. A server that only says whether a
+//! ciphertext decrypted to valid PKCS#7 padding is enough to decrypt
+//! anything encrypted under its key, one byte at a time.
+
+use rand::Rng;
+
+use crate::{
+    byte_util::base64_decode,
+    modes::Padding,
+    oracle::PaddingOracle,
+    set_2::{aes::{decrypt_aes_128_cbc, encrypt_aes_128_cbc, pkcs7_unpad, pkcs7_valid}, oracle::random_key},
+};
+
+const CHALLENGE_17: &str = include_str!("../../data/set_3/ch17.txt");
+
+/// Encrypts with AES 128 CBC under a fixed random key and a fresh random IV
+/// per message, and answers padding queries.
+pub struct CbcPaddingOracle {
+    key: Vec<u8>,
+}
+
+impl CbcPaddingOracle {
+    pub fn new<R: Rng>(rng: &mut R) -> CbcPaddingOracle {
+        CbcPaddingOracle { key: random_key(rng) }
+    }
+
+    /// Returns (iv, ciphertext).
+    pub fn encrypt<R: Rng>(&self, plaintext: &[u8], rng: &mut R) -> ([u8; 16], Vec<u8>) {
+        let mut iv = [0u8; 16];
+        rng.fill_bytes(&mut iv);
+        (iv, encrypt_aes_128_cbc(plaintext, &self.key, Some(&iv), Padding::Pkcs7))
+    }
+
+    /// Encrypt one of the ten challenge 17 strings, picked at random.
+    pub fn encrypt_random_string<R: Rng>(&self, rng: &mut R) -> ([u8; 16], Vec<u8>) {
+        let strings = challenge_17_strings();
+        let plaintext = &strings[rng.gen_range(0..strings.len())];
+        self.encrypt(plaintext, rng)
+    }
+}
+
+impl PaddingOracle for CbcPaddingOracle {
+    fn padding_valid(&mut self, iv: &[u8], ciphertext: &[u8]) -> bool {
+        if iv.len() != 16 || ciphertext.is_empty() || !ciphertext.len().is_multiple_of(16) {
+            return false;
+        }
+        let plaintext = decrypt_aes_128_cbc(ciphertext, &self.key, Some(iv), Padding::None).unwrap();
+        pkcs7_valid(&plaintext, 16)
+    }
+}
+
+/// The ten strings the challenge 17 oracle picks from.
+pub fn challenge_17_strings() -> Vec<Vec<u8>> {
+    CHALLENGE_17.lines().map(|l| base64_decode(l.as_bytes())).collect()
+}
+
+/// Decrypt a CBC ciphertext using only a padding oracle.
+///
+/// Each block is attacked on its own by sending it with a forged IV. The
+/// plaintext is the block's decryption XORed with the real previous block,
+/// the IV for the first, so finding the decryption is enough. Working from
+/// the last byte back, the forged IV is set so the known bytes decrypt to
+/// the pad value, and every value of the next byte is tried until the
+/// padding is valid.
+///
+/// Returns the plaintext with the padding stripped, or None if the oracle
+/// never accepts any guess. Wrap the oracle in an [`Instrumented`] to count
+/// the queries.
+///
+/// [`Instrumented`]: crate::oracle::Instrumented
+pub fn padding_oracle_attack<O: PaddingOracle>(oracle: &mut O, iv: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+    let mut plaintext = Vec::with_capacity(ciphertext.len());
+    let mut prev = iv;
+    for block in ciphertext.chunks(16) {
+        let decrypted = attack_block(oracle, prev, block)?;
+        plaintext.extend(decrypted.iter().zip(prev).map(|(d, p)| d ^ p));
+        prev = block;
+    }
+    pkcs7_unpad(&plaintext, 16).ok()
+}
+
+/// Find the raw block decryption of `block`, before it is XORed with the
+/// previous ciphertext block.
+fn attack_block<O: PaddingOracle>(oracle: &mut O, prev: &[u8], block: &[u8]) -> Option<[u8; 16]> {
+    let mut decrypted = [0u8; 16];
+    // Starting from the real previous block means the real padding shows
+    // through on the last block, which is the ambiguous case handled below.
+    let mut forged: [u8; 16] = prev.try_into().ok()?;
+    for pos in (0..16).rev() {
+        let pad = (16 - pos) as u8;
+        for i in pos + 1..16 {
+            forged[i] = decrypted[i] ^ pad;
+        }
+        let mut found = false;
+        for guess in 0..=255u8 {
+            forged[pos] = guess;
+            if !oracle.padding_valid(&forged, block) {
+                continue;
+            }
+            if pos == 15 {
+                // The padding might have been valid as \x02\x02 or longer
+                // rather than \x01. Changing the byte before only breaks
+                // the longer ones.
+                forged[14] ^= 1;
+                let single = oracle.padding_valid(&forged, block);
+                forged[14] ^= 1;
+                if !single {
+                    continue;
+                }
+            }
+            decrypted[pos] = guess ^ pad;
+            found = true;
+            break;
+        }
+        if !found {
+            return None;
+        }
+    }
+    Some(decrypted)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use crate::{modes::{BlockMode, Cbc}, oracle::Instrumented};
+
+    use super::*;
+
+    // S3C17
+    #[test]
+    fn padding_oracle_challenge_17() {
+        let mut rng = StdRng::seed_from_u64(1717);
+        let mut oracle = Instrumented::new(CbcPaddingOracle::new(&mut rng));
+        let strings = challenge_17_strings();
+        assert_eq!(10, strings.len());
+        for expected in strings {
+            let (iv, ct) = oracle.get_ref().encrypt(&expected, &mut rng);
+            let before = oracle.queries();
+            assert_eq!(expected, padding_oracle_attack(&mut oracle, &iv, &ct).unwrap());
+            let queries = oracle.queries() - before;
+            // At most every guess for every byte, plus a check per block.
+            assert!(queries <= ct.len() * 256 + ct.len() / 16 * 255, "{} queries", queries);
+        }
+
+        let (iv, ct) = oracle.get_ref().encrypt_random_string(&mut rng);
+        let actual = padding_oracle_attack(&mut oracle, &iv, &ct).unwrap();
+        assert!(challenge_17_strings().contains(&actual));
+    }
+
+    #[test]
+    fn padding_oracle_every_pad_length() {
+        // Each length ends with a different real padding showing through on
+        // the last block, covering the ambiguous last byte case.
+        let mut rng = StdRng::seed_from_u64(17);
+        let mut oracle = CbcPaddingOracle::new(&mut rng);
+        for len in 0..=33 {
+            let expected: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let (iv, ct) = oracle.encrypt(&expected, &mut rng);
+            assert_eq!(expected, padding_oracle_attack(&mut oracle, &iv, &ct).unwrap(), "length {}", len);
+        }
+    }
+
+    #[test]
+    fn padding_oracle_generic() {
+        // Any oracle will do, here CBC over the software AES.
+        let key = [9u8; 16];
+        let iv = [4u8; 16];
+        let expected = "Generic over the oracle implementation".as_bytes();
+        let ct = Cbc::new(crate::aes::soft::Aes::new(&key), &iv, Padding::Pkcs7).encrypt(expected);
+        let mut oracle = Instrumented::new(|iv: &[u8], ct: &[u8]| {
+            Cbc::new(crate::aes::soft::Aes::new(&key), iv, Padding::Pkcs7).decrypt(ct).is_ok()
+        });
+        assert_eq!(expected, padding_oracle_attack(&mut oracle, &iv, &ct).unwrap());
+        assert!(oracle.queries() <= ct.len() * 256 + ct.len() / 16 * 255, "{} queries", oracle.queries());
+    }
+
+    #[test]
+    fn padding_oracle_rejects_malformed_queries() {
+        let mut rng = StdRng::seed_from_u64(171);
+        let mut oracle = CbcPaddingOracle::new(&mut rng);
+        let (iv, ct) = oracle.encrypt(b"YELLOW SUBMARINE", &mut rng);
+        assert!(oracle.padding_valid(&iv, &ct));
+        assert!(!oracle.padding_valid(&iv[..15], &ct));
+        assert!(!oracle.padding_valid(&[iv.as_slice(), &[0]].concat(), &ct));
+        assert!(!oracle.padding_valid(&[], &ct));
+        assert!(!oracle.padding_valid(&iv, &ct[..31]));
+    }
+
+    #[test]
+    fn padding_oracle_gives_up_on_useless_oracle() {
+        let mut oracle = |_: &[u8], _: &[u8]| false;
+        assert_eq!(None, padding_oracle_attack(&mut oracle, &[0; 16], &[0; 32]));
+    }
+}
@@ -0,0 +1,96 @@
+//! Breaking CTR through random access writes. Editing re-encrypts the new
+//! text with the same keystream the old text used, so writing known bytes
+//! over the ciphertext hands back the keystream, and with it the original
+//! plaintext.
+
+use rand::Rng;
+
+use crate::{
+    byte_util::base64_decode,
+    oracle::EditOracle,
+    set_1::aes::decrypt_aes_128_ecb,
+    set_2::{
+        aes::{edit_aes_128_ctr, encrypt_aes_128_ctr},
+        oracle::random_key,
+    },
+};
+
+const CHALLENGE_7: &str = include_str!("../../data/set_1/ch7.txt");
+
+/// The plaintext for challenge 25, which is challenge 7's file decrypted
+/// under its ECB key.
+pub fn challenge_25_plaintext() -> Vec<u8> {
+    let ciphertext = base64_decode(CHALLENGE_7.replace('\n', "").as_bytes());
+    decrypt_aes_128_ecb(&ciphertext, "YELLOW SUBMARINE".as_bytes()).expect("challenge 7 is valid PKCS#7")
+}
+
+/// Holds a random CTR key and nonce, and exposes nothing but edits made
+/// with them. An edit starting past the end of the ciphertext is refused,
+/// leaving the ciphertext as it was.
+pub struct CtrEditOracle {
+    key: Vec<u8>,
+    nonce: u64,
+}
+
+impl CtrEditOracle {
+    /// Key a new oracle and encrypt `plaintext` under it, returning the
+    /// oracle and the ciphertext.
+    pub fn new<R: Rng>(plaintext: &[u8], rng: &mut R) -> (CtrEditOracle, Vec<u8>) {
+        let oracle = CtrEditOracle { key: random_key(rng), nonce: rng.gen() };
+        let ciphertext = encrypt_aes_128_ctr(plaintext, &oracle.key, oracle.nonce);
+        (oracle, ciphertext)
+    }
+}
+
+impl EditOracle for CtrEditOracle {
+    fn edit(&mut self, ciphertext: &[u8], offset: usize, newtext: &[u8]) -> Vec<u8> {
+        if offset > ciphertext.len() {
+            return ciphertext.to_vec();
+        }
+        edit_aes_128_ctr(ciphertext, &self.key, self.nonce, offset, newtext)
+    }
+}
+
+/// Recover the plaintext of a ciphertext in one edit. Overwriting it all
+/// with zeros returns the bare keystream.
+pub fn recover_ctr_plaintext<O: EditOracle>(oracle: &mut O, ciphertext: &[u8]) -> Vec<u8> {
+    let keystream = oracle.edit(ciphertext, 0, &vec![0; ciphertext.len()]);
+    ciphertext.iter().zip(keystream).map(|(c, k)| c ^ k).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::oracle::Instrumented;
+
+    use super::*;
+
+    // S4C25
+    #[test]
+    fn recover_ctr_plaintext_happy() {
+        let plaintext = challenge_25_plaintext();
+        assert!(plaintext.starts_with(b"I'm back and I'm ringin' the bell"));
+        let (oracle, ciphertext) = CtrEditOracle::new(&plaintext, &mut StdRng::seed_from_u64(25));
+        let mut oracle = Instrumented::new(oracle).with_budget(1);
+        assert_eq!(plaintext, recover_ctr_plaintext(&mut oracle, &ciphertext));
+        assert!(!oracle.budget_exhausted());
+    }
+
+    #[test]
+    fn ctr_edit_oracle_edits() {
+        let (mut oracle, ciphertext) = CtrEditOracle::new(b"attack at dawn", &mut StdRng::seed_from_u64(1));
+        let edited = oracle.edit(&ciphertext, 10, b"dusk");
+        assert_eq!(ciphertext[..10], edited[..10]);
+        assert_eq!(b"attack at dusk".to_vec(), recover_ctr_plaintext(&mut oracle, &edited));
+    }
+
+    #[test]
+    fn ctr_edit_oracle_refuses_past_end() {
+        let (mut oracle, ciphertext) = CtrEditOracle::new(b"attack at dawn", &mut StdRng::seed_from_u64(1));
+        assert_eq!(ciphertext, oracle.edit(&ciphertext, 15, b"!"));
+        assert_eq!(ciphertext, oracle.edit(&ciphertext, usize::MAX, b"!"));
+        // Appending right at the end is still allowed.
+        assert_eq!(15, oracle.edit(&ciphertext, 14, b"!").len());
+    }
+}
@@ -0,0 +1 @@
+pub mod ctr_edit;